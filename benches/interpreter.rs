@@ -0,0 +1,60 @@
+//! Timings of interpreter hot paths, run with `cargo bench --no-default-features`
+//!
+//! Uses a plain timing loop rather than a benchmark framework, so it runs on stable without
+//! extra dependencies. Every benchmark prints the average time per iteration.
+
+use chippie::interpreter::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+/// Instructions that fit between `BASE_ADDRESS` and the end of memory
+const PROGRAM_LENGTH: usize = (MEMORY_SIZE - BASE_ADDRESS) as usize / 2 - 1;
+
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    // Warm up caches and branch predictors before timing
+    for _ in 0..iterations / 10 {
+        f();
+    }
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let per_iteration = start.elapsed() / iterations;
+    println!("{:<40} {:>10.2?}", name, per_iteration);
+}
+
+/// Run a program of `PROGRAM_LENGTH` instructions from start to end
+fn run_program(interpreter: &mut Chip8Interpreter) {
+    interpreter.set_pc(BASE_ADDRESS, true).unwrap();
+    interpreter.run_cycles(PROGRAM_LENGTH).unwrap();
+    black_box(interpreter.state().pc);
+}
+
+fn main() {
+    // Zeroed memory takes the fast path past decode and dispatch
+    let mut zeroed = Chip8Interpreter::new();
+    bench("run zeroed memory", 20000, || run_program(&mut zeroed));
+
+    // `6000` is about the cheapest instruction that goes through decode and dispatch
+    let mut loads = Chip8Interpreter::new();
+    loads
+        .try_load_rom(&[0x60, 0x00].repeat(PROGRAM_LENGTH))
+        .unwrap();
+    bench("run LoadValue instructions", 20000, || {
+        run_program(&mut loads)
+    });
+
+    // The disassembly view decodes every row of memory each frame while paused
+    let rows = MEMORY_SIZE / 2;
+    let program = Chip8Interpreter::new();
+    bench("decode all rows", 2000, || {
+        black_box(program.disassemble_range(0, MEMORY_SIZE));
+    });
+    let mut cache = DecodeCache::default();
+    bench("decode all rows through DecodeCache", 2000, || {
+        for row in 0..rows {
+            black_box(cache.get(&program, row * 2));
+        }
+    });
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Name given to a range of memory, shown next to it in the memory and disassembly views
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct Annotation {
+    /// First address of the range
+    pub start: u16,
+    /// Last address of the range, inclusive
+    pub end: u16,
+    pub label: String,
+}
+
+impl Annotation {
+    /// Parse `START[-END] LABEL` with hexadecimal addresses, such as `0300-031f player sprite`
+    pub fn parse(source: &str) -> Option<Self> {
+        let (range, label) = source.trim().split_once(char::is_whitespace)?;
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let parse_address = |address: &str| {
+            let address = address.trim_start_matches("0x");
+            u16::from_str_radix(address, 16).ok()
+        };
+        let (start, end) = (parse_address(start)?, parse_address(end)?);
+        let label = label.trim();
+        (start <= end && !label.is_empty()).then(|| Self {
+            start,
+            end,
+            label: label.to_string(),
+        })
+    }
+
+    pub fn contains(&self, address: u16) -> bool {
+        (self.start..=self.end).contains(&address)
+    }
+
+    pub fn overlaps(&self, start: u16, end: u16) -> bool {
+        self.start <= end && start <= self.end
+    }
+}
+
+/// Annotations of every ROM that has any, keyed by `Chip8Interpreter::rom_hash`
+#[derive(Default, Deserialize, Serialize)]
+pub(crate) struct AnnotationStore {
+    roms: HashMap<u64, Vec<Annotation>>,
+}
+
+impl AnnotationStore {
+    /// Annotations of a ROM, ordered by start address
+    pub fn for_rom(&self, rom_hash: u64) -> &[Annotation] {
+        self.roms.get(&rom_hash).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn add(&mut self, rom_hash: u64, annotation: Annotation) {
+        let annotations = self.roms.entry(rom_hash).or_default();
+        let index = annotations.partition_point(|other| other.start <= annotation.start);
+        annotations.insert(index, annotation);
+    }
+
+    /// Remove the annotation at `index` of `for_rom`
+    pub fn remove(&mut self, rom_hash: u64, index: usize) {
+        if let Some(annotations) = self.roms.get_mut(&rom_hash) {
+            annotations.remove(index);
+            if annotations.is_empty() {
+                self.roms.remove(&rom_hash);
+            }
+        }
+    }
+
+    /// Label of the annotation covering `address`
+    ///
+    /// Overlapping annotations are treated as nested, so the narrowest one covering the address
+    /// wins. Between equally narrow annotations the one starting last wins.
+    pub fn label_at(&self, rom_hash: u64, address: u16) -> Option<&str> {
+        self.for_rom(rom_hash)
+            .iter()
+            .rev()
+            .filter(|annotation| annotation.contains(address))
+            .min_by_key(|annotation| annotation.end - annotation.start)
+            .map(|annotation| annotation.label.as_str())
+    }
+
+    /// Labels of every annotation that covers part of `start..=end`
+    pub fn labels_overlapping(
+        &self,
+        rom_hash: u64,
+        start: u16,
+        end: u16,
+    ) -> impl Iterator<Item = &str> + '_ {
+        self.for_rom(rom_hash)
+            .iter()
+            .filter(move |annotation| annotation.overlaps(start, end))
+            .map(|annotation| annotation.label.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotation(source: &str) -> Annotation {
+        Annotation::parse(source).unwrap()
+    }
+
+    #[test]
+    fn parses_ranges_and_single_addresses() {
+        let range = annotation("0x0300-031f  player sprite ");
+        assert_eq!((range.start, range.end), (0x300, 0x31f));
+        assert_eq!(range.label, "player sprite");
+
+        let single = annotation("2a0 score");
+        assert_eq!((single.start, single.end), (0x2a0, 0x2a0));
+
+        assert!(Annotation::parse("0320-0310 backwards").is_none());
+        assert!(Annotation::parse("0300").is_none());
+        assert!(Annotation::parse("zz label").is_none());
+    }
+
+    #[test]
+    fn lookup_prefers_the_narrowest_annotation() {
+        let mut store = AnnotationStore::default();
+        store.add(1, annotation("0300-03ff level data"));
+        store.add(1, annotation("0340-034f exit door"));
+        store.add(2, annotation("0300-03ff other rom"));
+
+        assert_eq!(store.label_at(1, 0x300), Some("level data"));
+        assert_eq!(store.label_at(1, 0x345), Some("exit door"));
+        assert_eq!(store.label_at(1, 0x400), None);
+        assert_eq!(store.label_at(2, 0x345), Some("other rom"));
+        assert_eq!(store.label_at(3, 0x345), None);
+
+        let overlapping: Vec<_> = store.labels_overlapping(1, 0x330, 0x340).collect();
+        assert_eq!(overlapping, ["level data", "exit door"]);
+    }
+
+    #[test]
+    fn annotations_stay_ordered_and_survive_a_round_trip() {
+        let mut store = AnnotationStore::default();
+        store.add(1, annotation("0400 b"));
+        store.add(1, annotation("0200 a"));
+        store.add(1, annotation("0300 c"));
+        let labels = |store: &AnnotationStore| -> Vec<String> {
+            store.for_rom(1).iter().map(|a| a.label.clone()).collect()
+        };
+        assert_eq!(labels(&store), ["a", "c", "b"]);
+
+        let mut store: AnnotationStore =
+            serde_json::from_str(&serde_json::to_string(&store).unwrap()).unwrap();
+        assert_eq!(labels(&store), ["a", "c", "b"]);
+
+        store.remove(1, 1);
+        assert_eq!(labels(&store), ["a", "b"]);
+        store.remove(1, 0);
+        store.remove(1, 0);
+        assert!(store.roms.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+mod cpal_sink;
+
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+pub(crate) use cpal_sink::CpalAudioSink;
+use serde::{Deserialize, Serialize};
+
+/// Shape of the tone played while the sound timer is running
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) enum Waveform {
+    /// The authentic buzzer sound
+    Square,
+    Sine,
+    Triangle,
+    Sawtooth,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 4] = [
+        Waveform::Square,
+        Waveform::Sine,
+        Waveform::Triangle,
+        Waveform::Sawtooth,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Waveform::Square => "Square",
+            Waveform::Sine => "Sine",
+            Waveform::Triangle => "Triangle",
+            Waveform::Sawtooth => "Sawtooth",
+        }
+    }
+
+    /// Value in `-1.0..=1.0` of the waveform at `phase`, measured in periods from `0.0` to `1.0`
+    #[cfg(any(test, all(feature = "audio", not(target_arch = "wasm32"))))]
+    pub fn sample(&self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+/// How the buzzer sounds
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct AudioConfig {
+    pub waveform: Waveform,
+    /// Frequency of the buzzer tone
+    pub pitch_hz: f32,
+    /// Output volume from `0.0` to `1.0`
+    pub volume: f32,
+    /// Shortest time a beep sounds for, in milliseconds
+    ///
+    /// The buzzer sounds while the sound timer is non-zero, so a sound timer of 1 only lasts a
+    /// single 60Hz tick and can fall between two frames entirely. Every beep is held for at least
+    /// this long to keep such beeps audible.
+    pub min_beep_ms: f32,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Square,
+            pitch_hz: 440.0,
+            volume: 0.25,
+            min_beep_ms: 50.0,
+        }
+    }
+}
+
+/// Destination for the sounds produced by the emulator
+pub(crate) trait AudioSink {
+    /// Start or stop the sound timer buzzer
+    fn set_buzzer(&mut self, playing: bool);
+    /// Play a short click, independent of the buzzer
+    fn click(&mut self);
+    /// Change how the buzzer sounds
+    fn set_config(&mut self, config: AudioConfig);
+}
+
+/// Sink used when no audio output is available
+pub(crate) struct SilentAudioSink;
+
+impl AudioSink for SilentAudioSink {
+    fn set_buzzer(&mut self, _playing: bool) {}
+
+    fn click(&mut self) {}
+
+    fn set_config(&mut self, _config: AudioConfig) {}
+}
+
+/// Best sink available in this build, falling back to silence if audio can't be played
+pub(crate) fn default_sink() -> Box<dyn AudioSink> {
+    #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+    if let Some(sink) = CpalAudioSink::new() {
+        return Box::new(sink);
+    }
+
+    Box::new(SilentAudioSink)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(waveform: Waveform) -> Vec<f32> {
+        [0.0, 0.25, 0.5, 0.75]
+            .into_iter()
+            .map(|phase| waveform.sample(phase))
+            .collect()
+    }
+
+    fn assert_close(actual: Vec<f32>, expected: [f32; 4]) {
+        for (actual, expected) in actual.into_iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-6,
+                "{} != {}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn waveforms_at_quarter_periods() {
+        assert_close(samples(Waveform::Square), [1.0, 1.0, -1.0, -1.0]);
+        assert_close(samples(Waveform::Sine), [0.0, 1.0, 0.0, -1.0]);
+        assert_close(samples(Waveform::Triangle), [-1.0, 0.0, 1.0, 0.0]);
+        assert_close(samples(Waveform::Sawtooth), [-1.0, -0.5, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn waveforms_stay_in_range() {
+        for waveform in Waveform::ALL {
+            for step in 0..100 {
+                let sample = waveform.sample(step as f32 / 100.0);
+                assert!(
+                    (-1.0..=1.0).contains(&sample),
+                    "{:?} gave {}",
+                    waveform,
+                    sample
+                );
+            }
+        }
+    }
+}
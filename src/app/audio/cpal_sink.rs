@@ -0,0 +1,91 @@
+use super::{AudioConfig, AudioSink};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::sync::{Arc, Mutex};
+
+/// Length of the collision click in seconds
+const CLICK_DURATION: f32 = 0.005;
+
+/// Sound generator state shared with the audio callback
+#[derive(Default)]
+struct Voice {
+    config: AudioConfig,
+    buzzing: bool,
+    /// Samples left to play of the current click
+    click_samples: usize,
+    /// Position within the current period of the buzzer tone
+    phase: f32,
+}
+
+impl Voice {
+    fn next_sample(&mut self, sample_rate: f32) -> f32 {
+        let value = if self.buzzing {
+            self.config.waveform.sample(self.phase)
+        } else if self.click_samples > 0 {
+            self.click_samples -= 1;
+            1.0
+        } else {
+            0.0
+        };
+
+        self.phase = (self.phase + self.config.pitch_hz / sample_rate).fract();
+        value * self.config.volume
+    }
+}
+
+/// Plays the emulator sounds on the default output device
+pub(crate) struct CpalAudioSink {
+    voice: Arc<Mutex<Voice>>,
+    sample_rate: f32,
+    /// Output stops when the stream is dropped
+    _stream: cpal::Stream,
+}
+
+impl CpalAudioSink {
+    /// Open the default output device, or `None` if there isn't a usable one
+    pub fn new() -> Option<Self> {
+        let device = cpal::default_host().default_output_device()?;
+        let supported = device.default_output_config().ok()?;
+        if supported.sample_format() != cpal::SampleFormat::F32 {
+            return None;
+        }
+
+        let config: cpal::StreamConfig = supported.into();
+        let sample_rate = config.sample_rate.0 as f32;
+        let channels = config.channels as usize;
+        let voice = Arc::new(Mutex::new(Voice::default()));
+        let callback_voice = voice.clone();
+        let stream = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    let mut voice = callback_voice.lock().unwrap();
+                    for frame in data.chunks_mut(channels) {
+                        frame.fill(voice.next_sample(sample_rate));
+                    }
+                },
+                |e| println!("Audio output error: {}", e),
+            )
+            .ok()?;
+        stream.play().ok()?;
+
+        Some(Self {
+            voice,
+            sample_rate,
+            _stream: stream,
+        })
+    }
+}
+
+impl AudioSink for CpalAudioSink {
+    fn set_buzzer(&mut self, playing: bool) {
+        self.voice.lock().unwrap().buzzing = playing;
+    }
+
+    fn click(&mut self) {
+        self.voice.lock().unwrap().click_samples = (CLICK_DURATION * self.sample_rate) as usize;
+    }
+
+    fn set_config(&mut self, config: AudioConfig) {
+        self.voice.lock().unwrap().config = config;
+    }
+}
@@ -0,0 +1,23 @@
+use crate::app::widgets::render_rgba;
+use crate::interpreter::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use arboard::{Clipboard, ImageData};
+use eframe::egui::Color32;
+
+/// Size in image pixels of a single CHIP-8 pixel in copied screens
+const CAPTURE_SCALE: usize = 8;
+
+/// Place an image of `screen` on the system clipboard
+///
+/// Fails if there is no clipboard available, e.g. when running without a display server.
+pub(crate) fn copy_screen(
+    screen: &[[u8; SCREEN_WIDTH]; SCREEN_HEIGHT],
+    foreground: Color32,
+    background: Color32,
+) -> Result<(), arboard::Error> {
+    let image = ImageData {
+        width: SCREEN_WIDTH * CAPTURE_SCALE,
+        height: SCREEN_HEIGHT * CAPTURE_SCALE,
+        bytes: render_rgba(screen, CAPTURE_SCALE, foreground, background).into(),
+    };
+    Clipboard::new()?.set_image(image)
+}
@@ -0,0 +1,107 @@
+use crate::interpreter::RPL_FLAG_COUNT;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// RPL user flags of every ROM that stored any, one file per ROM in a directory
+pub(crate) struct FlagStore {
+    directory: PathBuf,
+}
+
+impl FlagStore {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// File holding the flags of the ROM with `Chip8Interpreter::rom_hash` `rom_hash`
+    pub fn path(&self, rom_hash: u64) -> PathBuf {
+        self.directory.join(format!("{:016x}.flags", rom_hash))
+    }
+
+    /// Flags stored for a ROM, `None` if it never stored any
+    pub fn load(&self, rom_hash: u64) -> std::io::Result<Option<[u8; RPL_FLAG_COUNT]>> {
+        load_flags(&self.path(rom_hash))
+    }
+
+    pub fn save(&self, rom_hash: u64, flags: &[u8; RPL_FLAG_COUNT]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(self.path(rom_hash), flags)
+    }
+}
+
+/// Read a flag file, files of another length are cut short or padded with zeros
+fn load_flags(path: &Path) -> std::io::Result<Option<[u8; RPL_FLAG_COUNT]>> {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut flags = [0; RPL_FLAG_COUNT];
+    let len = contents.len().min(RPL_FLAG_COUNT);
+    flags[..len].copy_from_slice(&contents[..len]);
+    Ok(Some(flags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Chip8Config, Chip8Interpreter, Chip8Variant};
+
+    #[test]
+    fn flags_round_trip() {
+        let directory = std::env::temp_dir().join(format!("chippie-flags-{}", std::process::id()));
+        let store = FlagStore::new(&directory);
+        let flags = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        assert_eq!(store.load(0x1234).unwrap(), None);
+        store.save(0x1234, &flags).unwrap();
+        assert_eq!(store.load(0x1234).unwrap(), Some(flags));
+        assert_eq!(store.load(0x5678).unwrap(), None);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn short_flag_files_are_padded() {
+        let directory =
+            std::env::temp_dir().join(format!("chippie-short-flags-{}", std::process::id()));
+        let store = FlagStore::new(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(store.path(0x1234), [9, 9]).unwrap();
+
+        assert_eq!(store.load(0x1234).unwrap(), Some([9, 9, 0, 0, 0, 0, 0, 0]));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn flags_stored_by_a_program_survive_a_new_session() {
+        let directory =
+            std::env::temp_dir().join(format!("chippie-session-flags-{}", std::process::id()));
+        let store = FlagStore::new(&directory);
+        // Store V0 and V1 in the flags, then spin
+        let rom = [0x60, 0x12, 0x61, 0x34, 0xf1, 0x75, 0x12, 0x06];
+
+        let config = Chip8Config {
+            variant: Chip8Variant::SuperChip,
+            ..Default::default()
+        };
+        let mut interp = Chip8Interpreter::new();
+        interp.set_config(config);
+        interp.try_load_rom(&rom).unwrap();
+        interp.run_cycles(3).unwrap();
+        store.save(interp.rom_hash(), interp.rpl_flags()).unwrap();
+
+        let mut interp = Chip8Interpreter::new();
+        interp.set_config(config);
+        interp.try_load_rom(&rom).unwrap();
+        assert_eq!(interp.rpl_flags(), &[0; RPL_FLAG_COUNT]);
+        let flags = store.load(interp.rom_hash()).unwrap().unwrap();
+        interp.set_rpl_flags(flags);
+        assert_eq!(interp.rpl_flags(), &[0x12, 0x34, 0, 0, 0, 0, 0, 0]);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use crate::interpreter::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Render-side filter that keeps recently lit pixels visible for a few frames
+///
+/// Games that erase and redraw sprites every frame will often present a frame with the sprite
+/// missing. Holding pixels on for a short window hides this without touching the interpreter's
+/// screen buffer.
+pub(crate) struct FlickerFilter {
+    /// Number of frames a pixel stays visible after it was last lit
+    pub hold_frames: u8,
+    /// Frames elapsed since each pixel was last lit, saturating at `u8::MAX`
+    frames_since_lit: [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT],
+}
+
+impl Default for FlickerFilter {
+    fn default() -> Self {
+        Self {
+            hold_frames: 3,
+            frames_since_lit: [[u8::MAX; SCREEN_WIDTH]; SCREEN_HEIGHT],
+        }
+    }
+}
+
+impl FlickerFilter {
+    /// Record the screen contents presented this frame
+    pub fn update(&mut self, screen: &[[u8; SCREEN_WIDTH]; SCREEN_HEIGHT]) {
+        for (held_row, screen_row) in self.frames_since_lit.iter_mut().zip(screen.iter()) {
+            for (held, &pixel) in held_row.iter_mut().zip(screen_row.iter()) {
+                *held = if pixel != 0 {
+                    0
+                } else {
+                    held.saturating_add(1)
+                };
+            }
+        }
+    }
+
+    /// Whether the pixel should be displayed as lit even if it is currently off
+    pub fn is_held(&self, x: usize, y: usize) -> bool {
+        self.frames_since_lit[y][x] <= self.hold_frames
+    }
+
+    pub fn clear(&mut self) {
+        self.frames_since_lit = [[u8::MAX; SCREEN_WIDTH]; SCREEN_HEIGHT];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_stay_held_for_hold_frames_after_going_dark() {
+        let mut filter = FlickerFilter::default();
+        let mut screen = [[0; SCREEN_WIDTH]; SCREEN_HEIGHT];
+        assert!(!filter.is_held(5, 7));
+
+        screen[7][5] = 1;
+        filter.update(&screen);
+        assert!(filter.is_held(5, 7));
+        assert!(!filter.is_held(6, 7));
+
+        screen[7][5] = 0;
+        for _ in 0..filter.hold_frames {
+            filter.update(&screen);
+            assert!(filter.is_held(5, 7));
+        }
+        filter.update(&screen);
+        assert!(!filter.is_held(5, 7));
+    }
+
+    #[test]
+    fn relighting_restarts_the_hold_and_clear_drops_it() {
+        let mut filter = FlickerFilter {
+            hold_frames: 1,
+            ..Default::default()
+        };
+        let mut screen = [[0; SCREEN_WIDTH]; SCREEN_HEIGHT];
+        screen[0][0] = 2;
+        filter.update(&screen);
+        filter.update(&[[0; SCREEN_WIDTH]; SCREEN_HEIGHT]);
+        filter.update(&screen);
+        filter.update(&[[0; SCREEN_WIDTH]; SCREEN_HEIGHT]);
+        assert!(filter.is_held(0, 0));
+
+        filter.clear();
+        assert!(!filter.is_held(0, 0));
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+/// How register and memory values are displayed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) enum ValueFormat {
+    Decimal,
+    /// Two's complement signed decimal
+    Signed,
+    Hex,
+    Binary,
+}
+
+impl ValueFormat {
+    pub const ALL: [ValueFormat; 4] = [
+        ValueFormat::Decimal,
+        ValueFormat::Signed,
+        ValueFormat::Hex,
+        ValueFormat::Binary,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueFormat::Decimal => "Decimal",
+            ValueFormat::Signed => "Signed",
+            ValueFormat::Hex => "Hex",
+            ValueFormat::Binary => "Binary",
+        }
+    }
+}
+
+/// Format a byte with a fixed width for the given format
+pub(crate) fn format_value(value: u8, format: ValueFormat) -> String {
+    match format {
+        ValueFormat::Decimal => format!("{:3}", value),
+        ValueFormat::Signed => format!("{:4}", value as i8),
+        ValueFormat::Hex => format!("{:02x}", value),
+        ValueFormat::Binary => format!("{:08b}", value),
+    }
+}
+
+/// Format bytes as 16-bit words
+///
+/// Words are big-endian, the way CHIP-8 fetches instructions, unless `little_endian` is set. A
+/// trailing odd byte is shown on its own.
+pub(crate) fn format_words(bytes: &[u8], little_endian: bool) -> String {
+    bytes
+        .chunks(2)
+        .map(|word| match word {
+            [first, second] if little_endian => format!("{:02x}{:02x}", second, first),
+            [first, second] => format!("{:02x}{:02x}", first, second),
+            [byte] => format!("{:02x}", byte),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Printable ASCII characters of `bytes`, with a dot standing in for every other byte
+pub(crate) fn format_ascii(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&c| {
+            if c.is_ascii_graphic() {
+                c as char
+            } else {
+                '·'
+            }
+        })
+        .collect()
+}
+
+/// Rows of sixteen bytes for a hex view of `bytes`, each with the offset of its first byte
+pub(crate) fn hex_rows(bytes: &[u8]) -> impl ExactSizeIterator<Item = (usize, &[u8])> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(row, data)| (row * 16, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Chip8Interpreter, Chip8InterpreterError, MEMORY_SIZE};
+
+    #[test]
+    fn hex_rows_cover_files_too_large_to_load() {
+        let contents: Vec<u8> = (0..MEMORY_SIZE as usize + 5).map(|i| i as u8).collect();
+        assert!(matches!(
+            Chip8Interpreter::new().try_load_rom(&contents),
+            Err(Chip8InterpreterError::RomFileTooLarge)
+        ));
+
+        let rows: Vec<_> = hex_rows(&contents).collect();
+        assert_eq!(rows.len(), MEMORY_SIZE as usize / 16 + 1);
+        assert_eq!(rows[0], (0, &contents[..16]));
+        let (offset, data) = rows[rows.len() - 1];
+        assert_eq!(offset, MEMORY_SIZE as usize);
+        assert_eq!(data, &contents[MEMORY_SIZE as usize..]);
+        assert_eq!(
+            rows.iter().map(|(_, data)| data.len()).sum::<usize>(),
+            contents.len()
+        );
+    }
+
+    #[test]
+    fn words_group_pairs_of_bytes() {
+        let bytes = [0x00, 0xe0, 0x6a, 0x2b, 0xd0];
+        assert_eq!(format_words(&bytes[..4], false), "00e0 6a2b");
+        // Starting at an odd address pairs the bytes the other way
+        assert_eq!(format_words(&bytes[1..], false), "e06a 2bd0");
+        assert_eq!(format_words(&bytes, false), "00e0 6a2b d0");
+        assert_eq!(format_words(&[], false), "");
+    }
+
+    #[test]
+    fn little_endian_words_swap_the_bytes() {
+        let bytes = [0x12, 0x34, 0xab, 0xcd, 0xef];
+        assert_eq!(format_words(&bytes, false), "1234 abcd ef");
+        assert_eq!(format_words(&bytes, true), "3412 cdab ef");
+    }
+}
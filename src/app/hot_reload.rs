@@ -0,0 +1,33 @@
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+/// Time to wait for writes to settle before reporting a change
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Watches a ROM file on disk for modifications
+pub(crate) struct RomWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<DebouncedEvent>,
+}
+
+impl RomWatcher {
+    pub fn new(path: &Path) -> notify::Result<Self> {
+        let (sender, events) = channel();
+        let mut watcher = watcher(sender, DEBOUNCE_INTERVAL)?;
+        watcher.watch(path, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Whether the file changed since the last poll
+    pub fn poll(&self) -> bool {
+        self.events.try_iter().fold(false, |changed, event| {
+            changed || matches!(event, DebouncedEvent::Write(_) | DebouncedEvent::Create(_))
+        })
+    }
+}
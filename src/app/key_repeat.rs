@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+/// How held keys repeat, like the auto-repeat of a keyboard
+///
+/// The interpreter sees a held key as pressed for as long as it is held, so instructions that
+/// check whether a key is down are not affected by this. Repeating only adds a release and a new
+/// press of the key, which matters to `WaitForKey` when it waits for keys to be released, and to
+/// anything else counting key presses rather than looking at held keys.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct KeyRepeatConfig {
+    pub enabled: bool,
+    /// Time a key has to be held before it starts repeating, in milliseconds
+    pub delay_ms: f32,
+    /// Time between repeats once a key is repeating, in milliseconds
+    pub interval_ms: f32,
+}
+
+impl Default for KeyRepeatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            delay_ms: 500.0,
+            interval_ms: 100.0,
+        }
+    }
+}
+
+/// Tracks how long keys have been held to decide when they repeat
+#[derive(Default)]
+pub(crate) struct KeyRepeat {
+    /// Time in seconds each key has been held for, 0 for keys that aren't held
+    held_for: [f32; 16],
+}
+
+impl KeyRepeat {
+    /// Advance time by `dt` seconds with `keys` held, returning the mask of keys that repeat now
+    ///
+    /// A key repeats once after being held for the configured delay, and then once every interval.
+    /// A key that was just pressed never repeats in the same update.
+    pub fn update(&mut self, keys: u32, dt: f32, config: &KeyRepeatConfig) -> u32 {
+        let delay = config.delay_ms / 1000.0;
+        let interval = (config.interval_ms / 1000.0).max(f32::EPSILON);
+        let repeats_after = |held_for: f32| {
+            if held_for < delay {
+                0
+            } else {
+                ((held_for - delay) / interval) as u32 + 1
+            }
+        };
+
+        let mut repeated = 0;
+        for (key, held_for) in self.held_for.iter_mut().enumerate() {
+            if keys & (1u32 << key) == 0 {
+                *held_for = 0.0;
+                continue;
+            }
+
+            let before = *held_for;
+            *held_for += dt;
+            if config.enabled && before > 0.0 && repeats_after(*held_for) > repeats_after(before) {
+                repeated |= 1u32 << key;
+            }
+        }
+        repeated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: KeyRepeatConfig = KeyRepeatConfig {
+        enabled: true,
+        delay_ms: 500.0,
+        interval_ms: 250.0,
+    };
+
+    /// Frames, of 125ms each, on which the keys held in `frames` repeated
+    fn repeating_frames(frames: &[u32], config: &KeyRepeatConfig) -> Vec<(usize, u32)> {
+        let mut repeat = KeyRepeat::default();
+        frames
+            .iter()
+            .enumerate()
+            .map(|(frame, &keys)| (frame + 1, repeat.update(keys, 0.125, config)))
+            .filter(|&(_, repeated)| repeated != 0)
+            .collect()
+    }
+
+    #[test]
+    fn held_keys_repeat_after_the_delay_and_then_every_interval() {
+        let frames = [1 << 3; 9];
+        assert_eq!(
+            repeating_frames(&frames, &CONFIG),
+            [(4, 1 << 3), (6, 1 << 3), (8, 1 << 3)]
+        );
+
+        let disabled = KeyRepeatConfig {
+            enabled: false,
+            ..CONFIG
+        };
+        assert_eq!(repeating_frames(&frames, &disabled), []);
+    }
+
+    #[test]
+    fn releasing_a_key_restarts_the_delay() {
+        // Key 3 is let go of on frame 4, key 9 is held throughout
+        let frames = [
+            1 << 3,
+            1 << 3 | 1 << 9,
+            1 << 3 | 1 << 9,
+            1 << 9,
+            1 << 3 | 1 << 9,
+            1 << 3 | 1 << 9,
+            1 << 3 | 1 << 9,
+            1 << 3 | 1 << 9,
+        ];
+        assert_eq!(
+            repeating_frames(&frames, &CONFIG),
+            [(5, 1 << 9), (7, 1 << 9), (8, 1 << 3)]
+        );
+    }
+
+    #[test]
+    fn new_presses_never_repeat_straight_away() {
+        let mut repeat = KeyRepeat::default();
+        assert_eq!(repeat.update(1 << 2, 2.0, &CONFIG), 0);
+        // The key then repeats on the schedule it would have had, at 2.25 seconds
+        assert_eq!(repeat.update(1 << 2, 0.125, &CONFIG), 0);
+        assert_eq!(repeat.update(1 << 2, 0.125, &CONFIG), 1 << 2);
+    }
+}
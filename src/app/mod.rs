@@ -1,20 +1,175 @@
+mod annotations;
+mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+mod clipboard;
+#[cfg(not(target_arch = "wasm32"))]
+mod flag_store;
+mod flicker;
+mod format;
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload;
+mod key_repeat;
+mod pacing;
+#[cfg(not(target_arch = "wasm32"))]
+mod rejected_file;
+mod settings;
+mod watch;
 mod widgets;
 
 use eframe::egui::{
-    Align, Color32, FontSelection, Pos2, Rect, RichText, Rounding, Sense, TextEdit, TextStyle,
-    Vec2, Widget,
+    Color32, FontSelection, Pos2, Rect, RichText, Rounding, Sense, TextEdit, TextStyle, Vec2,
+    Widget,
 };
 use eframe::{egui, epi};
 
+#[cfg(target_arch = "wasm32")]
+use std::cell::RefCell;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+#[cfg(target_arch = "wasm32")]
+use std::rc::Rc;
+
+use crate::app::annotations::{Annotation, AnnotationStore};
+use crate::app::audio::{AudioSink, Waveform};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::app::flag_store::FlagStore;
+use crate::app::flicker::FlickerFilter;
+use crate::app::format::{format_ascii, format_value, format_words, ValueFormat};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::app::hot_reload::RomWatcher;
+use crate::app::key_repeat::KeyRepeat;
+use crate::app::pacing::{cycles_for_frame, FrameHistory};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::app::rejected_file::RejectedFile;
+use crate::app::settings::Settings;
+use crate::app::watch::Watch;
 use crate::app::widgets::*;
+use crate::interpreter::console::Chip8Console;
 use crate::interpreter::*;
-use crate::programs::PROGRAMS;
+
+/// Most instructions executed by stepping out of a subroutine before giving up
+const STEP_OUT_MAX_TICKS: usize = 1_000_000;
+
+/// Rows shown above the PC when the disassembly view follows it
+const DISASSEMBLY_CONTEXT_ROWS: usize = 8;
+
+/// Number of single steps over which the highlight of a changed register fades out
+const REGISTER_HIGHLIGHT_STEPS: u8 = 3;
+
+/// Storage key of the persisted `Settings`
+const SETTINGS_KEY: &str = "settings";
+
+/// Storage key of the persisted `AnnotationStore`
+const ANNOTATIONS_KEY: &str = "annotations";
+
+/// Storage key of the persisted playlist
+#[cfg(not(target_arch = "wasm32"))]
+const PLAYLIST_KEY: &str = "playlist";
+
+/// Foreground and background pixel colors offered as presets
+const SCREEN_COLOR_PRESETS: [(&str, Color32, Color32); 4] = [
+    ("Green", Color32::DARK_GREEN, Color32::BLACK),
+    ("Amber", Color32::from_rgb(0xff, 0xb0, 0x00), Color32::BLACK),
+    ("White", Color32::WHITE, Color32::BLACK),
+    ("Paper", Color32::BLACK, Color32::WHITE),
+];
+
+/// Application name, shown in the window title
+const APP_NAME: &str = "Chippie";
+
+/// File extensions offered by the open dialog
+const ROM_EXTENSIONS: &[&str] = &["ch8", "c8", "sc8", "xo8"];
+
+/// Number of frames kept in the frame timing graph
+const FRAME_HISTORY_LENGTH: usize = 120;
+
+/// Most timer periods made up for in one frame, after that the timers fall behind instead
+const MAX_TIMER_CATCH_UP: u32 = 4;
 
 pub struct TemplateApp {
     interpreter: Chip8Interpreter,
     running: bool,
     lock_disassembly_to_pc: bool,
     disassembly_starts_at_one: bool,
+    show_frame_timing: bool,
+    show_last_cleared_screen: bool,
+    /// Ticks executed and cycles spent in each of the most recent frames
+    frame_history: FrameHistory,
+    /// Input time at which the timers last counted down while running
+    last_timer_tick: Option<f64>,
+    /// Fraction of an instruction left over from the previous frame
+    cycle_budget: f64,
+    show_console: bool,
+    console: Chip8Console,
+    show_watches: bool,
+    watches: Vec<Watch>,
+    watch_input: String,
+    /// Pause when a breaking watch fails to evaluate instead of silently never breaking
+    pause_on_eval_error: bool,
+    /// Decoded instructions of the disassembly view, kept while memory doesn't change
+    decode_cache: DecodeCache,
+    show_annotations: bool,
+    annotations: AnnotationStore,
+    show_opcode_legend: bool,
+    annotation_input: String,
+    show_keypad: bool,
+    /// Keys held down on the on screen keypad
+    keypad_keys: u32,
+    key_repeat: KeyRepeat,
+    console_input: String,
+    console_output: String,
+    /// Set when a draw collided during the most recent frame
+    collided_this_frame: bool,
+    /// Input time until which the buzzer sounds even if the sound timer has already run out
+    buzz_until: f64,
+    /// Single steps left until each register's change highlight has faded
+    register_highlight: [u8; REGISTER_COUNT],
+    /// Pause after every executed draw instruction
+    break_on_draw: bool,
+    /// Pause after the next executed draw instruction only
+    pause_on_next_draw: bool,
+    /// `Call` or `Return` that most recently failed on the call stack, highlighted while PC is on it
+    stack_error_at: Option<u16>,
+    /// Events drained from the interpreter by the last tick
+    tick_events: Vec<Chip8Event>,
+    flicker_filter: FlickerFilter,
+    settings: Settings,
+    /// Quick save slot
+    save_state: Option<Chip8SaveBundle>,
+    audio: Box<dyn AudioSink>,
+    /// Path of the ROM file loaded from disk, `None` if the loaded ROM didn't come from a file
+    #[cfg(not(target_arch = "wasm32"))]
+    rom_path: Option<PathBuf>,
+    /// Watches `rom_path` for changes while hot reloading is enabled
+    #[cfg(not(target_arch = "wasm32"))]
+    rom_watcher: Option<RomWatcher>,
+    #[cfg(not(target_arch = "wasm32"))]
+    show_playlist: bool,
+    /// Error shown in a window until dismissed
+    error_message: Option<String>,
+    /// ROM picked in the browser file dialog, which delivers it asynchronously
+    #[cfg(target_arch = "wasm32")]
+    picked_rom: Rc<RefCell<Option<Vec<u8>>>>,
+    /// Last file that failed to load as a ROM, shown in the file inspector until closed
+    #[cfg(not(target_arch = "wasm32"))]
+    rejected_file: Option<RejectedFile>,
+    /// ROM files to cycle through
+    #[cfg(not(target_arch = "wasm32"))]
+    playlist: Vec<PathBuf>,
+    /// Entry of `playlist` that is loaded, `None` if the loaded ROM didn't come from the playlist
+    #[cfg(not(target_arch = "wasm32"))]
+    playlist_index: Option<usize>,
+    /// Path typed into the playlist window
+    #[cfg(not(target_arch = "wasm32"))]
+    playlist_input: String,
+    /// ROM the RPL flags are persisted for, `None` for ROMs not loaded from disk
+    #[cfg(not(target_arch = "wasm32"))]
+    flags_rom_hash: Option<u64>,
+    /// RPL flags as they were last loaded or saved
+    #[cfg(not(target_arch = "wasm32"))]
+    saved_flags: [u8; RPL_FLAG_COUNT],
+    /// Title the window was last given
+    window_title: String,
 }
 
 impl Default for TemplateApp {
@@ -24,12 +179,287 @@ impl Default for TemplateApp {
             running: false,
             lock_disassembly_to_pc: true,
             disassembly_starts_at_one: false,
+            show_frame_timing: false,
+            show_last_cleared_screen: false,
+            frame_history: FrameHistory::new(FRAME_HISTORY_LENGTH),
+            last_timer_tick: None,
+            cycle_budget: 0.0,
+            show_console: false,
+            console: Default::default(),
+            show_watches: false,
+            watches: Vec::new(),
+            watch_input: String::new(),
+            pause_on_eval_error: true,
+            decode_cache: Default::default(),
+            show_annotations: false,
+            annotations: Default::default(),
+            show_opcode_legend: false,
+            annotation_input: String::new(),
+            show_keypad: cfg!(target_arch = "wasm32"),
+            keypad_keys: 0,
+            key_repeat: Default::default(),
+            console_input: String::new(),
+            console_output: String::new(),
+            collided_this_frame: false,
+            buzz_until: 0.0,
+            register_highlight: [0; REGISTER_COUNT],
+            break_on_draw: false,
+            pause_on_next_draw: false,
+            stack_error_at: None,
+            tick_events: Vec::new(),
+            flicker_filter: Default::default(),
+            settings: Default::default(),
+            save_state: None,
+            audio: audio::default_sink(),
+            #[cfg(not(target_arch = "wasm32"))]
+            rom_path: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            rom_watcher: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            show_playlist: false,
+            error_message: None,
+            #[cfg(target_arch = "wasm32")]
+            picked_rom: Default::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            rejected_file: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            playlist: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            playlist_index: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            playlist_input: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            flags_rom_hash: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            saved_flags: [0; RPL_FLAG_COUNT],
+            window_title: String::new(),
         }
     }
 }
 
 impl TemplateApp {
+    /// Reset the interpreter, along with the debug state if configured to
+    fn reset(&mut self) {
+        self.interpreter.reset();
+        self.flicker_filter.clear();
+        if self.interpreter.config().reset_clears_debug_state {
+            self.clear_debug_state();
+        }
+    }
+
+    /// Remove all breakpoints and watches and turn off the other debugging aids
+    fn clear_debug_state(&mut self) {
+        self.interpreter.clear_debug_state();
+        self.watches.clear();
+        self.register_highlight = [0; REGISTER_COUNT];
+        self.break_on_draw = false;
+    }
+
+    /// Reset the interpreter and load the ROM file at `path`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_rom_file(&mut self, path: PathBuf) {
+        self.save_flags();
+        self.flags_rom_hash = None;
+        match std::fs::read(&path) {
+            Ok(rom) => {
+                self.reset();
+                match self.interpreter.try_load_rom(&rom) {
+                    Ok(()) => {
+                        self.rejected_file = None;
+                        self.load_flags();
+                    }
+                    Err(e) => {
+                        self.error_message =
+                            Some(format!("Unable to load {}: {}", path.display(), e));
+                        self.rejected_file = Some(RejectedFile {
+                            path: path.clone(),
+                            error: e.to_string(),
+                            contents: rom,
+                        });
+                    }
+                }
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Unable to read {}: {}", path.display(), e))
+            }
+        }
+
+        self.rom_watcher = None;
+        self.rom_path = Some(path);
+        self.playlist_index = None;
+    }
+
+    /// Restore the RPL flags persisted for the loaded ROM
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_flags(&mut self) {
+        let rom_hash = self.interpreter.rom_hash();
+        self.flags_rom_hash = Some(rom_hash);
+        if !self.settings.flag_directory.is_empty() {
+            match FlagStore::new(&self.settings.flag_directory).load(rom_hash) {
+                Ok(Some(flags)) => self.interpreter.set_rpl_flags(flags),
+                Ok(None) => {}
+                Err(e) => self.error_message = Some(format!("Unable to load flags: {}", e)),
+            }
+        }
+        self.saved_flags = *self.interpreter.rpl_flags();
+    }
+
+    /// Persist the RPL flags if the program changed them since they were last saved
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_flags(&mut self) {
+        let flags = *self.interpreter.rpl_flags();
+        let rom_hash = match self.flags_rom_hash {
+            Some(rom_hash) if flags != self.saved_flags => rom_hash,
+            _ => return,
+        };
+        // Failed saves aren't retried, so that they are reported only once
+        self.saved_flags = flags;
+        if !self.settings.flag_directory.is_empty() {
+            if let Err(e) = FlagStore::new(&self.settings.flag_directory).save(rom_hash, &flags) {
+                self.error_message = Some(format!("Unable to save flags: {}", e));
+            }
+        }
+    }
+
+    /// Replace the playlist and load its first entry
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_playlist(&mut self, playlist: Vec<PathBuf>) {
+        self.playlist = playlist;
+        self.load_playlist_entry(0);
+    }
+
+    /// Open the browser file dialog, the ROM is loaded by `load_picked_rom` once it has been read
+    #[cfg(target_arch = "wasm32")]
+    fn pick_rom_file(&self, ctx: &egui::Context) {
+        let picked_rom = Rc::clone(&self.picked_rom);
+        let ctx = ctx.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let dialog = rfd::AsyncFileDialog::new().add_filter("CHIP-8 ROM", ROM_EXTENSIONS);
+            if let Some(file) = dialog.pick_file().await {
+                *picked_rom.borrow_mut() = Some(file.read().await);
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Reset the interpreter and load the ROM picked by `pick_rom_file`, if it has arrived
+    #[cfg(target_arch = "wasm32")]
+    fn load_picked_rom(&mut self) {
+        let rom = match self.picked_rom.borrow_mut().take() {
+            Some(rom) => rom,
+            None => return,
+        };
+        self.load_rom_bytes(&rom);
+    }
+
+    /// Reset the interpreter and load `rom`, for ROMs that don't come from a file on disk
+    fn load_rom_bytes(&mut self, rom: &[u8]) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.save_flags();
+            self.flags_rom_hash = None;
+            self.rom_watcher = None;
+            self.rom_path = None;
+            self.playlist_index = None;
+        }
+        self.reset();
+        if let Err(e) = self.interpreter.try_load_rom(rom) {
+            self.error_message = Some(format!("Unable to load ROM: {}", e));
+        }
+    }
+
+    /// Show the name of the loaded ROM file in the window title
+    fn update_window_title(&mut self, frame: &epi::Frame) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let title = match self.rom_path.as_ref().and_then(|path| path.file_name()) {
+            Some(name) => format!("{} - {}", APP_NAME, name.to_string_lossy()),
+            None => APP_NAME.to_string(),
+        };
+        #[cfg(target_arch = "wasm32")]
+        let title = APP_NAME.to_string();
+
+        if title != self.window_title {
+            frame.set_window_title(&title);
+            self.window_title = title;
+        }
+    }
+
+    /// Load the first of the files dropped onto the window, ignoring the rest
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = match ctx.input().raw.dropped_files.first().cloned() {
+            Some(dropped) => dropped,
+            None => return,
+        };
+        // Native drops only carry a path, web drops only the contents
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = dropped.path {
+            self.load_rom_file(path);
+            return;
+        }
+        if let Some(bytes) = dropped.bytes {
+            self.load_rom_bytes(&bytes);
+        }
+    }
+
+    /// Load the playlist entry at `index`, wrapping around past the end
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_playlist_entry(&mut self, index: usize) {
+        if self.playlist.is_empty() {
+            return;
+        }
+
+        let index = index % self.playlist.len();
+        self.load_rom_file(self.playlist[index].clone());
+        self.playlist_index = Some(index);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_hot_reload(&mut self, enabled: bool) {
+        self.rom_watcher = None;
+        if let (true, Some(path)) = (enabled, &self.rom_path) {
+            match RomWatcher::new(path) {
+                Ok(watcher) => self.rom_watcher = Some(watcher),
+                Err(e) => println!("Unable to watch {}: {:?}", path.display(), e),
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_hot_reload(&mut self) {
+        let changed = self
+            .rom_watcher
+            .as_ref()
+            .map_or(false, |watcher| watcher.poll());
+        if let (true, Some(path)) = (changed, self.rom_path.clone()) {
+            // A reload that resets the interpreter drops the flags, keep what the program stored
+            self.save_flags();
+            let result = std::fs::read(&path)
+                .map_err(|e| format!("{:?}", e))
+                .and_then(|rom| {
+                    self.interpreter
+                        .reload_rom(&rom)
+                        .map_err(|e| format!("{:?}", e))
+                });
+            match result {
+                Ok(true) => {
+                    self.flicker_filter.clear();
+                    self.load_flags();
+                }
+                Ok(false) => {}
+                Err(e) => println!("Unable to reload {}: {}", path.display(), e),
+            }
+        }
+    }
+
     fn handle_input(&mut self, ctx: &egui::Context) {
+        let dt = ctx.input().unstable_dt;
+
+        // Keys typed into a text field must not leak into the emulator as keypad presses
+        if ctx.wants_keyboard_input() {
+            self.apply_keys(self.keypad_keys, dt);
+            return;
+        }
+
         let input = ctx.input();
         let mut keys: u32 = 0;
         if input.key_down(egui::Key::Num1) {
@@ -81,66 +511,623 @@ impl TemplateApp {
             keys |= 1u32 << 0xf;
         }
 
-        self.interpreter.set_input_keys(keys);
+        self.apply_keys(keys | self.keypad_keys, dt);
+    }
+
+    /// Feed the held keys to the interpreter as individual press and release edges
+    ///
+    /// `dt` is the time since the previous call in seconds, used to repeat held keys.
+    fn apply_keys(&mut self, keys: u32, dt: f32) {
+        let (pressed, released) = key_edges(self.interpreter.state().input_keys, keys);
+        let repeated = self.key_repeat.update(keys, dt, &self.settings.key_repeat);
+        for key in 0..16u8 {
+            let result = if pressed & (1u32 << key) != 0 {
+                self.interpreter.press_key(key)
+            } else if released & (1u32 << key) != 0 {
+                self.interpreter.release_key(key)
+            } else if repeated & (1u32 << key) != 0 {
+                self.interpreter
+                    .release_key(key)
+                    .and_then(|()| self.interpreter.press_key(key))
+            } else {
+                Ok(())
+            };
+            if let Err(e) = result {
+                println!("Unable to update key {:x}: {}", key, e);
+            }
+        }
+    }
+
+    /// Tick the interpreter, reporting its state if it panics and crash reports are enabled
+    fn tick(&mut self) -> Result<(), Chip8InterpreterError> {
+        self.stack_error_at = None;
+        let result = if self.settings.crash_reports {
+            with_crash_report(&mut self.interpreter, Chip8Interpreter::tick)
+        } else {
+            self.interpreter.tick()
+        };
+        self.tick_events = self.interpreter.take_events();
+        for event in &self.tick_events {
+            if let Chip8Event::StackOverflow(address) | Chip8Event::StackUnderflow(address) = *event
+            {
+                self.stack_error_at = Some(address);
+            }
+        }
+        result
+    }
+
+    /// Execute a single instruction, highlighting the registers it changed
+    fn step(&mut self) {
+        let before = self.interpreter.state().registers;
+        // Single steps don't stop at breakpoints, ticking again resumes from them
+        let result = match self.tick() {
+            Err(Chip8InterpreterError::BreakpointHit(_)) => self.tick(),
+            result => result,
+        };
+        if let Err(e) = result {
+            println!("Unable to step: {}", e);
+        }
+
+        let after = self.interpreter.state().registers;
+        for (i, highlight) in self.register_highlight.iter_mut().enumerate() {
+            *highlight = if before[i] != after[i] {
+                REGISTER_HIGHLIGHT_STEPS
+            } else {
+                highlight.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Count the timers down at `TIMER_FREQUENCY` of wall clock time, `now` is in seconds
+    fn update_timers(&mut self, now: f64) {
+        let period = 1.0 / TIMER_FREQUENCY as f64;
+        let mut last = self.last_timer_tick.unwrap_or(now);
+        if now - last > MAX_TIMER_CATCH_UP as f64 * period {
+            last = now - MAX_TIMER_CATCH_UP as f64 * period;
+        }
+        while now - last >= period {
+            self.interpreter.tick_timers();
+            last += period;
+        }
+        self.last_timer_tick = Some(last);
     }
 }
 
 impl epi::App for TemplateApp {
     fn update(&mut self, ctx: &egui::Context, frame: &epi::Frame) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_hot_reload();
+        #[cfg(target_arch = "wasm32")]
+        self.load_picked_rom();
+        self.handle_dropped_files(ctx);
+        self.update_window_title(frame);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.save_flags();
+
+        // Single steps count the timers down by instruction, free running uses the wall clock
+        self.interpreter.set_tick_drives_timers(!self.running);
+        if !self.running {
+            self.last_timer_tick = None;
+        }
+
         if self.running {
+            // Changes are only highlighted while single stepping
+            self.register_highlight = [0; REGISTER_COUNT];
             self.handle_input(ctx);
-            for _ in 0..20 {
-                self.interpreter.tick();
+            let start_ticks = self.interpreter.ticks();
+            let start_cycles = self.interpreter.cycles();
+            self.collided_this_frame = false;
+            let mut sound_started = false;
+            // Execute as many instructions as the clock rate allows for the time since last frame,
+            // unless the speed is fixed
+            let frame_delta = ctx.input().unstable_dt as f64;
+            let ticks_this_frame = if self.settings.fixed_ticks_per_frame {
+                self.settings.ticks_per_frame as u64
+            } else {
+                let (ticks, cycle_budget) = cycles_for_frame(
+                    self.cycle_budget,
+                    frame_delta,
+                    self.interpreter.clock_rate(),
+                );
+                self.cycle_budget = cycle_budget;
+                ticks
+            };
+            for _ in 0..ticks_this_frame {
+                match self.tick() {
+                    Err(Chip8InterpreterError::BreakpointHit(_)) => {
+                        self.running = false;
+                        break;
+                    }
+                    Err(e @ Chip8InterpreterError::WatchpointHit { .. }) => {
+                        println!("{}", e);
+                        self.running = false;
+                        break;
+                    }
+                    Err(
+                        e @ (Chip8InterpreterError::CallStackDepthExceeded { .. }
+                        | Chip8InterpreterError::CallStackEmpty { .. }),
+                    ) if self.settings.pause_on_stack_error => {
+                        println!("{}", e);
+                        self.running = false;
+                        break;
+                    }
+                    _ => {}
+                }
+                if self.interpreter.is_halted() {
+                    self.running = false;
+                    break;
+                }
+                self.collided_this_frame |= self.tick_events.contains(&Chip8Event::Collision);
+                sound_started |= self.tick_events.contains(&Chip8Event::SoundStarted);
+
+                if let Some(Chip8Instruction::Draw { .. }) = self.interpreter.last_instruction() {
+                    if self.break_on_draw || self.pause_on_next_draw {
+                        self.running = false;
+                        self.pause_on_next_draw = false;
+                        break;
+                    }
+                }
+
+                let state = self.interpreter.state();
+                if self
+                    .watches
+                    .iter()
+                    .any(|watch| watch.should_break(state, self.pause_on_eval_error))
+                {
+                    self.running = false;
+                    break;
+                }
+            }
+            self.update_timers(ctx.input().time);
+            self.frame_history.record(
+                self.interpreter.ticks() - start_ticks,
+                self.interpreter.cycles() - start_cycles,
+                frame_delta,
+            );
+            self.flicker_filter.update(&self.interpreter.state().screen);
+
+            if self.settings.click_on_collision && self.collided_this_frame {
+                self.audio.click();
+            }
+            if sound_started {
+                let min_beep = self.settings.audio.min_beep_ms as f64 / 1000.0;
+                self.buzz_until = ctx.input().time + min_beep;
+            }
+
+            // Nothing can change until a key is pressed, and key presses trigger a repaint anyway
+            let state = self.interpreter.state();
+            let idle = !self.interpreter.screen_dirty()
+                && self.interpreter.is_waiting_for_key()
+                && state.dt == 0
+                && state.st == 0
+                && ctx.input().time >= self.buzz_until;
+            if !idle {
+                ctx.request_repaint();
             }
-            ctx.request_repaint();
         }
 
+        // Only pay for screen captures while they can be looked at
+        self.interpreter
+            .set_capture_cleared_screen(self.show_last_cleared_screen);
+        self.audio.set_config(self.settings.audio);
+        let beep_held = ctx.input().time < self.buzz_until;
+        self.audio
+            .set_buzzer(self.running && (self.interpreter.should_buzz() || beep_held));
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
                     if ui.button("Open").clicked() {
-                        self.interpreter.reset();
-                        if let Err(e) = self.interpreter.try_load_rom(&PROGRAMS[0].data) {
-                            println!("Unable to load rom: {:?}", e);
+                        ui.close_menu();
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if let Some(path) = rom_file_dialog().pick_file() {
+                            self.load_rom_file(path);
+                        }
+                        #[cfg(target_arch = "wasm32")]
+                        self.pick_rom_file(ctx);
+                    }
+                    ui.separator();
+                    if ui.button("Save State").clicked() {
+                        self.save_state = Some(self.interpreter.save_bundle());
+                    }
+                    if ui
+                        .add_enabled(self.save_state.is_some(), egui::Button::new("Load State"))
+                        .clicked()
+                    {
+                        if let Some(bundle) = self.save_state.clone() {
+                            if let Err(e) = self.interpreter.load_bundle(bundle) {
+                                println!("Unable to load state: {:?}", e);
+                            }
+                        }
+                    }
+                    ui.separator();
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        let mut hot_reload = self.rom_watcher.is_some();
+                        let response = ui.add_enabled(
+                            self.rom_path.is_some(),
+                            egui::Checkbox::new(&mut hot_reload, "Hot Reload"),
+                        );
+                        if response.changed() {
+                            self.set_hot_reload(hot_reload);
+                        }
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Export Disassembly").clicked() {
+                        ui.close_menu();
+                        let dialog = export_file_dialog(self.rom_path.as_ref(), "Assembly", "asm");
+                        if let Some(path) = dialog.save_file() {
+                            let result = std::fs::File::create(path)
+                                .and_then(|mut file| self.interpreter.write_disassembly(&mut file));
+                            if let Err(e) = result {
+                                println!("Unable to export disassembly: {:?}", e);
+                            }
+                        }
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Export Disassembly as JSON").clicked() {
+                        ui.close_menu();
+                        let dialog = export_file_dialog(self.rom_path.as_ref(), "JSON", "json");
+                        if let Some(path) = dialog.save_file() {
+                            let rom_hash = self.interpreter.rom_hash();
+                            let label_at = |address| {
+                                self.annotations
+                                    .label_at(rom_hash, address)
+                                    .map(str::to_string)
+                            };
+                            let result = std::fs::File::create(path)
+                                .map_err(serde_json::Error::io)
+                                .and_then(|file| {
+                                    self.interpreter
+                                        .write_disassembly_json(&mut &file, label_at)
+                                });
+                            if let Err(e) = result {
+                                println!("Unable to export disassembly: {:?}", e);
+                            }
+                        }
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Copy Screen").clicked() {
+                        if let Err(e) = clipboard::copy_screen(
+                            &self.interpreter.state().screen,
+                            self.settings.screen_foreground,
+                            self.settings.screen_background,
+                        ) {
+                            println!("Unable to copy screen: {}", e);
                         }
                     }
                     if ui.button("Quit").clicked() {
                         frame.quit();
                     }
                 });
+                ui.menu_button("Emulation", |ui| {
+                    ui.label("Variant");
+                    let mut config = *self.interpreter.config();
+                    for variant in Chip8Variant::ALL {
+                        ui.radio_value(&mut config.variant, variant, variant.to_string());
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let mut clock_hz = config.clock_hz();
+                        let response = ui.add(
+                            egui::DragValue::new(&mut clock_hz)
+                                .clamp_range(TIMER_FREQUENCY..=20000)
+                                .suffix(" Hz"),
+                        );
+                        if response.changed() {
+                            config.clock_hz = Some(clock_hz);
+                        }
+                        ui.label("Clock speed");
+                    });
+                    if ui
+                        .add_enabled(
+                            config.clock_hz.is_some(),
+                            egui::Button::new("Reset to variant default"),
+                        )
+                        .clicked()
+                    {
+                        config.clock_hz = None;
+                    }
+                    ui.separator();
+                    ui.checkbox(
+                        &mut config.trap_uninitialized_memory,
+                        "Stop on uninitialized memory",
+                    );
+                    ui.checkbox(
+                        &mut config.reseed_on_reset,
+                        "Reseed the random number generator on reset",
+                    );
+                    ui.checkbox(
+                        &mut config.reset_clears_debug_state,
+                        "Reset clears breakpoints and watches",
+                    );
+                    ui.separator();
+                    for quirk in config.quirks() {
+                        let mut enabled = quirk.enabled;
+                        if ui.checkbox(&mut enabled, quirk.description).changed() {
+                            if let Err(e) = config.set_quirk(quirk.name, enabled) {
+                                println!("Unable to set quirk: {}", e);
+                            }
+                        }
+                    }
+                    self.interpreter.set_config(config);
+                    ui.separator();
+                    let key_repeat = &mut self.settings.key_repeat;
+                    ui.checkbox(&mut key_repeat.enabled, "Repeat held keys");
+                    ui.add_enabled(
+                        key_repeat.enabled,
+                        egui::Slider::new(&mut key_repeat.delay_ms, 100.0..=1000.0)
+                            .suffix(" ms")
+                            .text("Repeat delay"),
+                    );
+                    ui.add_enabled(
+                        key_repeat.enabled,
+                        egui::Slider::new(&mut key_repeat.interval_ms, 20.0..=500.0)
+                            .suffix(" ms")
+                            .text("Repeat interval"),
+                    );
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let mut depth = self.interpreter.history_depth();
+                        let response =
+                            ui.add(egui::DragValue::new(&mut depth).clamp_range(0..=10000));
+                        if response.changed() {
+                            self.interpreter.set_history_depth(depth);
+                        }
+                        ui.label("Rewind history")
+                            .on_hover_text("Instructions that can be stepped back");
+                    });
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.horizontal(|ui| {
+                        ui.label("Flag directory").on_hover_text(
+                            "Where SUPER-CHIP flag registers are kept between sessions, empty to \
+                             not keep them",
+                        );
+                        ui.text_edit_singleline(&mut self.settings.flag_directory);
+                    });
+                    ui.checkbox(
+                        &mut self.settings.pause_on_stack_error,
+                        "Pause on call stack overflow or underflow",
+                    );
+                    ui.checkbox(&mut self.settings.crash_reports, "Print state on crash")
+                        .on_hover_text("Dump the interpreter state to stderr if it panics");
+                    if ui.button("Clear Debug State").clicked() {
+                        self.clear_debug_state();
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.show_frame_timing, "Frame Timing");
+                    ui.checkbox(&mut self.show_console, "Console");
+                    ui.checkbox(&mut self.show_keypad, "Keypad");
+                    ui.checkbox(&mut self.show_watches, "Watches");
+                    ui.checkbox(&mut self.show_annotations, "Annotations");
+                    ui.checkbox(&mut self.show_opcode_legend, "Opcode Legend");
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.checkbox(&mut self.show_playlist, "Playlist");
+                    ui.checkbox(&mut self.show_last_cleared_screen, "Last Cleared Screen");
+                    ui.separator();
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.screen_border_width, 0..=8)
+                            .text("Screen border"),
+                    );
+                    ui.checkbox(&mut self.settings.pin_screen_scale, "Fixed screen scale");
+                    ui.add_enabled(
+                        self.settings.pin_screen_scale,
+                        egui::Slider::new(&mut self.settings.screen_scale, 1..=8).suffix("x"),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_srgba(&mut self.settings.screen_border_color);
+                        ui.label("Border color");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_srgba(&mut self.settings.screen_foreground);
+                        ui.color_edit_button_srgba(&mut self.settings.screen_background);
+                        ui.label("Pixel colors");
+                    });
+                    ui.horizontal(|ui| {
+                        for (name, foreground, background) in SCREEN_COLOR_PRESETS {
+                            if ui.small_button(name).clicked() {
+                                self.settings.screen_foreground = foreground;
+                                self.settings.screen_background = background;
+                            }
+                        }
+                    });
+                    ui.checkbox(
+                        &mut self.settings.flash_border_on_collision,
+                        "Flash border on collision",
+                    );
+                    ui.checkbox(&mut self.settings.click_on_collision, "Click on collision");
+                    ui.separator();
+                    ui.label("Buzzer");
+                    for waveform in Waveform::ALL {
+                        ui.radio_value(
+                            &mut self.settings.audio.waveform,
+                            waveform,
+                            waveform.name(),
+                        );
+                    }
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.audio.pitch_hz, 110.0..=1760.0)
+                            .logarithmic(true)
+                            .text("Pitch (Hz)"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.audio.volume, 0.0..=1.0)
+                            .text("Volume"),
+                    );
+                    ui.add(
+                        egui::Slider::new(&mut self.settings.audio.min_beep_ms, 0.0..=200.0)
+                            .suffix(" ms")
+                            .text("Shortest beep"),
+                    );
+                    ui.separator();
+                    ui.checkbox(&mut self.settings.reduce_flicker, "Reduce flicker");
+                    ui.add_enabled(
+                        self.settings.reduce_flicker,
+                        egui::Slider::new(&mut self.flicker_filter.hold_frames, 1..=10)
+                            .text("Hold frames"),
+                    );
+                });
             });
         });
 
+        let mut dismissed = false;
+        if let Some(message) = &self.error_message {
+            egui::Window::new("Error")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(message.as_str());
+                    dismissed = ui.button("OK").clicked();
+                });
+        }
+        if dismissed {
+            self.error_message = None;
+        }
+
+        // Stepping, stepping back and loading states change the screen while paused
+        if !self.running && self.interpreter.screen_dirty() {
+            self.flicker_filter.update(&self.interpreter.state().screen);
+        }
+        // Held pixels fade every frame while running, so filtered screens change every frame
+        let screen_changed =
+            self.interpreter.screen_dirty() || (self.running && self.settings.reduce_flicker);
+        self.interpreter.clear_screen_dirty();
+
+        // Annotations are stored per ROM
+        let rom_hash = self.interpreter.rom_hash();
+
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::Window::new("Screen").show(ctx, |ui| {
                 let state = self.interpreter.state();
-                ui.add(Chip8Screen::new(&state));
+                let border_color =
+                    if self.settings.flash_border_on_collision && self.collided_this_frame {
+                        Color32::WHITE
+                    } else {
+                        self.settings.screen_border_color
+                    };
+                let mut screen = Chip8Screen::new(&state.screen)
+                    .with_changed(screen_changed)
+                    .with_border(self.settings.screen_border_width, border_color)
+                    .with_colors(
+                        self.settings.screen_foreground,
+                        self.settings.screen_background,
+                    );
+                if self.settings.reduce_flicker {
+                    screen = screen.with_flicker_filter(&self.flicker_filter);
+                }
+                if self.settings.pin_screen_scale {
+                    screen = screen.with_scale(self.settings.screen_scale);
+                }
+                ui.add(screen);
             });
 
             egui::Window::new("Interpreter").show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     if ui.button("🔁").clicked() {
-                        self.interpreter.reset();
+                        self.reset();
+                    }
+                    let can_step_back = self.interpreter.history_len() > 0;
+                    if ui
+                        .add_enabled(can_step_back, egui::Button::new("⏴"))
+                        .on_hover_text("Step back")
+                        .clicked()
+                    {
+                        if let Err(e) = self.interpreter.step_back() {
+                            println!("Unable to step back: {}", e);
+                        }
                     }
                     if ui.button("⏵").clicked() {
-                        self.interpreter.tick();
+                        self.step();
+                    }
+                    let in_subroutine = self.interpreter.state().sp > 0;
+                    if ui
+                        .add_enabled(in_subroutine, egui::Button::new("⤴"))
+                        .on_hover_text("Run until the current subroutine returns")
+                        .clicked()
+                    {
+                        if let Err(e) = self.interpreter.step_out(STEP_OUT_MAX_TICKS) {
+                            println!("Unable to step out: {}", e);
+                        }
                     }
 
                     let toggle_run_icon = if self.running { "⏸" } else { "▶" };
                     if ui.button(toggle_run_icon).clicked() {
                         self.running = !self.running;
                     }
+                    if ui
+                        .button("⏭")
+                        .on_hover_text("Continue to next draw")
+                        .clicked()
+                    {
+                        self.running = true;
+                        self.pause_on_next_draw = true;
+                    }
+
+                    ui.checkbox(&mut self.break_on_draw, "Break on draw");
+
+                    let mut timers_frozen = self.interpreter.timers_frozen();
+                    if ui.checkbox(&mut timers_frozen, "Freeze timers").changed() {
+                        self.interpreter.freeze_timers(timers_frozen);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.settings.fixed_ticks_per_frame, "Fixed speed")
+                        .on_hover_text(
+                            "Execute a fixed number of instructions every frame instead of \
+                             following the clock rate, so the speed depends on the frame rate",
+                        );
+                    ui.add_enabled(
+                        self.settings.fixed_ticks_per_frame,
+                        egui::Slider::new(&mut self.settings.ticks_per_frame, 1..=100)
+                            .text("Instructions per frame"),
+                    );
                 });
 
+                if self.interpreter.is_halted() {
+                    ui.label("Program exited");
+                }
+                if !self.interpreter.verify_font_intact() {
+                    ui.colored_label(Color32::YELLOW, "⚠ Font memory has been overwritten");
+                }
+
                 ui.separator();
-                ui.label("Registers");
+                ui.horizontal(|ui| {
+                    ui.label("Registers");
+                    egui::ComboBox::from_id_source("register_format")
+                        .selected_text(self.settings.register_format.name())
+                        .show_ui(ui, |ui| {
+                            for format in ValueFormat::ALL {
+                                ui.selectable_value(
+                                    &mut self.settings.register_format,
+                                    format,
+                                    format.name(),
+                                );
+                            }
+                        });
+                });
 
                 egui::Grid::new("register_view")
                     .striped(true)
                     .show(ui, |ui| {
                         let state = self.interpreter.state();
                         for i in 0..REGISTER_COUNT {
-                            ui.monospace(format!("V{:x}: {:3}", i, state.registers[i]));
+                            let mut text = RichText::new(format!(
+                                "V{:x}: {}",
+                                i,
+                                format_value(state.registers[i], self.settings.register_format)
+                            ))
+                            .monospace();
+                            if self.register_highlight[i] > 0 {
+                                let strength = self.register_highlight[i] as f32
+                                    / REGISTER_HIGHLIGHT_STEPS as f32;
+                                text = text.background_color(
+                                    Color32::from_rgb(110, 90, 0).linear_multiply(strength),
+                                );
+                            }
+                            ui.label(text);
                             if i > 0 && i % 4 == 3 {
                                 ui.end_row();
                             } else {
@@ -158,6 +1145,17 @@ impl epi::App for TemplateApp {
                     ui.monospace(format!(" | I: {:04x}", state.i));
                     ui.monospace(format!(" | ST: {:3}", state.st));
                     ui.monospace(format!(" | DT: {:3}", state.dt));
+                    if let Some(count) = self.interpreter.instructions_until_timer_tick() {
+                        ui.label(RichText::new(format!(" ⏱ {}", count)).monospace().weak())
+                            .on_hover_text("Instructions until the timers next count down");
+                    }
+
+                    let speaker = if self.interpreter.should_buzz() {
+                        RichText::new(" 🔊").color(Color32::YELLOW)
+                    } else {
+                        RichText::new(" 🔈").color(Color32::DARK_GRAY)
+                    };
+                    ui.label(speaker.monospace());
                 });
 
                 ui.separator();
@@ -181,6 +1179,9 @@ impl epi::App for TemplateApp {
                     });
             });
 
+            // Address and whether to clear the stack, applied once the disassembly is drawn
+            let mut set_pc_request = None;
+            let mut toggled_breakpoint = None;
             egui::Window::new("Disassembly").show(ctx, |ui| {
                 let state = self.interpreter.state();
                 let row_count = (MEMORY_SIZE as usize / 2) + 1;
@@ -197,83 +1198,446 @@ impl epi::App for TemplateApp {
                         &mut self.lock_disassembly_to_pc,
                         "Lock disassembly view to PC",
                     );
+                    egui::ComboBox::from_id_source("disassembly_style")
+                        .selected_text(self.settings.disassembly_style.name())
+                        .show_ui(ui, |ui| {
+                            for style in DisassemblyStyle::ALL {
+                                ui.selectable_value(
+                                    &mut self.settings.disassembly_style,
+                                    style,
+                                    style.name(),
+                                );
+                            }
+                        });
                 });
 
-                egui::ScrollArea::vertical()
+                let row_height = ui.text_style_height(&TextStyle::Monospace);
+                let mut scroll_area = egui::ScrollArea::vertical()
                     .id_source("disassembly_view")
-                    .auto_shrink([false, true])
-                    .show(ui, |ui| {
-                        if self.running {
-                            ui.monospace("Disassembly is unavailable while running");
-                        } else {
-                            for row in 0..row_count {
-                                let address =
-                                    row * 2 + if self.disassembly_starts_at_one { 1 } else { 0 };
-                                let text = if let Ok(opcode) =
-                                    self.interpreter.try_read_instruction(address)
-                                {
-                                    format!("{:04x}:  {}", address, opcode)
+                    .auto_shrink([false, true]);
+                if self.lock_disassembly_to_pc {
+                    // Only visible rows are built, so scroll by offset rather than to the PC row
+                    let row_spacing = row_height + ui.spacing().item_spacing.y;
+                    let top_row = (state.pc as usize / 2).saturating_sub(DISASSEMBLY_CONTEXT_ROWS);
+                    scroll_area = scroll_area.vertical_scroll_offset(top_row as f32 * row_spacing);
+                }
+
+                if self.running {
+                    ui.monospace("Disassembly is unavailable while running");
+                } else {
+                    scroll_area.show_rows(ui, row_height, row_count, |ui, rows| {
+                        for row in rows {
+                            let address =
+                                row * 2 + if self.disassembly_starts_at_one { 1 } else { 0 };
+                            let decoded = self.decode_cache.get(&self.interpreter, address as u16);
+                            let mut text = if let Ok(instruction) = &decoded.instruction {
+                                format!(
+                                    "{:04x}:  {}",
+                                    address,
+                                    instruction.format(self.settings.disassembly_style)
+                                )
+                            } else {
+                                format!("{:04x}:", address)
+                            };
+                            // Only label the first row of each annotated range
+                            let annotation = self.annotations.label_at(rom_hash, address as u16);
+                            let previous_label = address.checked_sub(2).and_then(|previous| {
+                                self.annotations.label_at(rom_hash, previous as u16)
+                            });
+                            if let Some(label) =
+                                annotation.filter(|&label| Some(label) != previous_label)
+                            {
+                                text.push_str(&format!("  ; {}", label));
+                            }
+                            let has_breakpoint =
+                                self.interpreter.breakpoints().contains(&(address as u16));
+                            text.insert_str(0, if has_breakpoint { "● " } else { "  " });
+                            let mut label = RichText::new(text).monospace();
+                            if address == (state.pc as usize) {
+                                let faulted = self.stack_error_at == Some(state.pc);
+                                label = label.background_color(if faulted {
+                                    Color32::DARK_RED
                                 } else {
-                                    format!("{:04x}:", address)
-                                };
-                                let mut label = RichText::new(text).monospace();
-                                if address == (state.pc as usize) {
-                                    label = label.background_color(Color32::BLUE);
-                                }
+                                    Color32::BLUE
+                                });
+                            }
 
-                                let response = ui.label(label);
-                                if self.lock_disassembly_to_pc && address == (state.pc as usize) {
-                                    response.scroll_to_me(Some(Align::Center));
-                                }
+                            let response = ui.add(egui::Label::new(label).sense(Sense::click()));
+                            if response.double_clicked() {
+                                toggled_breakpoint = Some(address as u16);
                             }
+                            response.context_menu(|ui| {
+                                if ui.button("Set PC here").clicked() {
+                                    set_pc_request = Some((address as u16, false));
+                                    ui.close_menu();
+                                }
+                                if ui.button("Set PC here and clear stack").clicked() {
+                                    set_pc_request = Some((address as u16, true));
+                                    ui.close_menu();
+                                }
+                                if ui.button("Toggle breakpoint").clicked() {
+                                    toggled_breakpoint = Some(address as u16);
+                                    ui.close_menu();
+                                }
+                            });
                         }
                     });
+                }
             });
+            if let Some((address, clear_stack)) = set_pc_request {
+                if let Err(e) = self.interpreter.set_pc(address, clear_stack) {
+                    println!("Unable to set PC: {:?}", e);
+                }
+            }
+            if let Some(address) = toggled_breakpoint {
+                if !self.interpreter.remove_breakpoint(address) {
+                    self.interpreter.add_breakpoint(address);
+                }
+            }
+
+            let speed = self
+                .frame_history
+                .achieved_speed(self.interpreter.clock_rate());
+            egui::Window::new("Frame Timing")
+                .open(&mut self.show_frame_timing)
+                .show(ctx, |ui| {
+                    match speed {
+                        Some(speed) => ui.label(format!(
+                            "Speed: {:.0}% of {} Hz",
+                            speed * 100.0,
+                            self.interpreter.clock_rate()
+                        )),
+                        None => ui.label("Speed: not running"),
+                    };
+                    let values = self
+                        .frame_history
+                        .cycles()
+                        .enumerate()
+                        .map(|(frame, cycles)| egui::plot::Value::new(frame as f64, cycles as f64));
+                    let line = egui::plot::Line::new(egui::plot::Values::from_values_iter(values));
+
+                    ui.label("Cycles spent per frame");
+                    egui::plot::Plot::new("frame_timing_plot")
+                        .height(120.0)
+                        .include_y(0.0)
+                        .show(ui, |plot_ui| plot_ui.line(line));
+                });
+
+            egui::Window::new("Last Cleared Screen")
+                .open(&mut self.show_last_cleared_screen)
+                .show(ctx, |ui| match self.interpreter.last_cleared_screen() {
+                    Some(screen) => {
+                        ui.add(
+                            Chip8Screen::new(screen)
+                                .with_changed(screen_changed)
+                                .with_colors(
+                                    self.settings.screen_foreground,
+                                    self.settings.screen_background,
+                                ),
+                        );
+                    }
+                    None => {
+                        ui.label("The screen hasn't been cleared yet");
+                    }
+                });
+
+            egui::Window::new("Keypad")
+                .open(&mut self.show_keypad)
+                .show(ctx, |ui| {
+                    ui.add(Chip8Keypad::new(&mut self.keypad_keys));
+                });
+            // Keys held when the keypad gets closed would otherwise never be released
+            if !self.show_keypad {
+                self.keypad_keys = 0;
+            }
+
+            egui::Window::new("Watches")
+                .open(&mut self.show_watches)
+                .show(ctx, |ui| {
+                    let state = self.interpreter.state();
+                    let mut removed = None;
+                    egui::Grid::new("watches").striped(true).show(ui, |ui| {
+                        for (index, watch) in self.watches.iter_mut().enumerate() {
+                            ui.monospace(&watch.source);
+                            match watch.evaluate(state) {
+                                Ok(value) => ui.monospace(format!("{} ({:#x})", value, value)),
+                                Err(e) => ui.colored_label(Color32::RED, e.to_string()),
+                            };
+                            ui.checkbox(&mut watch.break_when_true, "Break");
+                            if ui.small_button("🗑").clicked() {
+                                removed = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(index) = removed {
+                        self.watches.remove(index);
+                    }
+
+                    let response = ui.add(
+                        TextEdit::singleline(&mut self.watch_input)
+                            .font(TextStyle::Monospace)
+                            .hint_text("v0 == 0x10"),
+                    );
+                    if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                        let source = std::mem::take(&mut self.watch_input);
+                        if !source.trim().is_empty() {
+                            self.watches.push(Watch::new(source.trim().to_string()));
+                        }
+                        response.request_focus();
+                    }
+                    ui.checkbox(
+                        &mut self.pause_on_eval_error,
+                        "Pause when a breaking watch can't be evaluated",
+                    );
+                });
+
+            egui::Window::new("Opcode Legend")
+                .open(&mut self.show_opcode_legend)
+                .vscroll(true)
+                .show(ctx, |ui| {
+                    egui::Grid::new("opcode_legend")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Opcode");
+                            ui.strong("Example");
+                            ui.strong("Category");
+                            ui.strong("Variants");
+                            ui.end_row();
+
+                            for info in opcode_legend() {
+                                ui.monospace(info.pattern);
+                                ui.monospace(info.example.to_string());
+                                ui.label(info.category.to_string());
+                                let variants: Vec<String> =
+                                    info.variants.iter().map(ToString::to_string).collect();
+                                ui.label(variants.join(", "));
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            egui::Window::new("Annotations")
+                .open(&mut self.show_annotations)
+                .show(ctx, |ui| {
+                    let mut removed = None;
+                    egui::Grid::new("annotations").striped(true).show(ui, |ui| {
+                        for (index, annotation) in
+                            self.annotations.for_rom(rom_hash).iter().enumerate()
+                        {
+                            ui.monospace(format!(
+                                "{:04x}-{:04x}",
+                                annotation.start, annotation.end
+                            ));
+                            ui.label(annotation.label.as_str());
+                            if ui.small_button("🗑").clicked() {
+                                removed = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(index) = removed {
+                        self.annotations.remove(rom_hash, index);
+                    }
+
+                    let response = ui.add(
+                        TextEdit::singleline(&mut self.annotation_input)
+                            .hint_text("0300-031f player sprite"),
+                    );
+                    if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                        match Annotation::parse(&self.annotation_input) {
+                            Some(annotation) => {
+                                self.annotations.add(rom_hash, annotation);
+                                self.annotation_input.clear();
+                            }
+                            None => {
+                                println!("Unable to parse annotation {}", self.annotation_input)
+                            }
+                        }
+                        response.request_focus();
+                    }
+                });
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(file) = &self.rejected_file {
+                let mut open = true;
+                egui::Window::new("File Inspector")
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        ui.label(format!(
+                            "{} is not a loadable ROM: {}",
+                            file.path.display(),
+                            file.error
+                        ));
+                        ui.label(format!("{} bytes", file.contents.len()));
+                        ui.separator();
+                        ui.add(HexView::new("file_inspector", &file.contents));
+                    });
+                if !open {
+                    self.rejected_file = None;
+                }
+            }
+
+            // Playlist entry to load, applied once the playlist window is drawn
+            #[cfg(not(target_arch = "wasm32"))]
+            let mut load = None;
+            #[cfg(not(target_arch = "wasm32"))]
+            egui::Window::new("Playlist")
+                .open(&mut self.show_playlist)
+                .show(ctx, |ui| {
+                    let mut removed = None;
+                    ui.horizontal(|ui| {
+                        let len = self.playlist.len();
+                        if ui.button("⏮").clicked() && len > 0 {
+                            load = Some(
+                                self.playlist_index
+                                    .map_or(len - 1, |index| (index + len - 1) % len),
+                            );
+                        }
+                        if ui.button("⏭").clicked() {
+                            load = Some(self.playlist_index.map_or(0, |index| index + 1));
+                        }
+                    });
+
+                    egui::Grid::new("playlist").striped(true).show(ui, |ui| {
+                        for (index, path) in self.playlist.iter().enumerate() {
+                            let name = path
+                                .file_name()
+                                .map(|name| name.to_string_lossy().into_owned())
+                                .unwrap_or_else(|| path.display().to_string());
+                            let selected = self.playlist_index == Some(index);
+                            if ui.selectable_label(selected, name).clicked() {
+                                load = Some(index);
+                            }
+                            if ui.small_button("🗑").clicked() {
+                                removed = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                    let response = ui.add(
+                        TextEdit::singleline(&mut self.playlist_input)
+                            .hint_text("Path to ROM file"),
+                    );
+                    if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                        let path = std::mem::take(&mut self.playlist_input);
+                        if !path.trim().is_empty() {
+                            self.playlist.push(PathBuf::from(path.trim()));
+                        }
+                        response.request_focus();
+                    }
+
+                    if let Some(index) = removed {
+                        self.playlist.remove(index);
+                        self.playlist_index = match self.playlist_index {
+                            Some(current) if index < current => Some(current - 1),
+                            Some(current) if index == current => None,
+                            current => current,
+                        };
+                    }
+                });
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(index) = load {
+                self.load_playlist_entry(index);
+            }
+
+            egui::Window::new("Console")
+                .open(&mut self.show_console)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical()
+                        .id_source("console_output")
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            ui.monospace(&self.console_output);
+                        });
+
+                    let response = ui.add(
+                        TextEdit::singleline(&mut self.console_input)
+                            .font(TextStyle::Monospace)
+                            .desired_width(f32::INFINITY),
+                    );
+                    if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+                        let line = std::mem::take(&mut self.console_input);
+                        self.console_output.push_str(&format!("> {}\n", line));
+                        match self.console.execute(&mut self.interpreter, &line) {
+                            Ok(output) => self.console_output.push_str(&output),
+                            Err(e) => self.console_output.push_str(&format!("{:?}", e)),
+                        }
+                        if !self.console_output.ends_with('\n') {
+                            self.console_output.push('\n');
+                        }
+                        response.request_focus();
+                    }
+                });
 
             egui::Window::new("Memory").show(ctx, |ui| {
                 let state = self.interpreter.state();
-                egui::ScrollArea::vertical()
-                    .id_source("memory_view")
-                    .auto_shrink([false, true])
-                    .show(ui, |ui| {
-                        if self.running {
-                            ui.monospace("Memory view is unavailable while running");
-                        } else {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.settings.memory_word_view, "Group as 16-bit words");
+                    ui.add_enabled(
+                        self.settings.memory_word_view,
+                        egui::Checkbox::new(
+                            &mut self.settings.memory_words_little_endian,
+                            "Little-endian",
+                        ),
+                    );
+                });
+
+                // Word rows follow the disassembly alignment so both views show the same opcodes
+                let offset = if self.settings.memory_word_view && self.disassembly_starts_at_one {
+                    1
+                } else {
+                    0
+                };
+
+                let row_height = ui.text_style_height(&TextStyle::Monospace);
+                let row_count = (state.memory.len() - offset).div_ceil(16);
+                if self.running {
+                    ui.monospace("Memory view is unavailable while running");
+                } else {
+                    egui::ScrollArea::vertical()
+                        .id_source("memory_view")
+                        .auto_shrink([false, true])
+                        .show_rows(ui, row_height, row_count, |ui, visible_rows| {
                             egui::Grid::new("hex_view").striped(true).show(ui, |ui| {
-                                for (row_start, row_data) in state.memory.chunks(16).enumerate() {
-                                    ui.monospace(format!("{:04x}  ", row_start * 16));
+                                let rows = state.memory[offset..]
+                                    .chunks(16)
+                                    .enumerate()
+                                    .skip(visible_rows.start)
+                                    .take(visible_rows.len());
+                                for (row_start, row_data) in rows {
+                                    ui.monospace(format!("{:04x}  ", row_start * 16 + offset));
 
                                     ui.horizontal(|ui| {
-                                        for (i, byte) in row_data.iter().enumerate() {
-                                            if i == 7 {
-                                                ui.monospace(format!("{:02x} ", byte));
-                                            } else {
-                                                ui.monospace(format!("{:02x}", byte));
-                                            }
+                                        if self.settings.memory_word_view {
+                                            ui.monospace(format_words(
+                                                row_data,
+                                                self.settings.memory_words_little_endian,
+                                            ));
+                                        } else {
+                                            hex_bytes(ui, row_data);
                                         }
                                     });
 
                                     ui.horizontal(|ui| {
                                         ui.monospace(" ");
-                                        ui.monospace(
-                                            row_data
-                                                .iter()
-                                                .map(|&c| {
-                                                    if c.is_ascii_graphic() {
-                                                        c as char
-                                                    } else {
-                                                        '·'
-                                                    }
-                                                })
-                                                .collect::<String>(),
-                                        );
+                                        ui.monospace(format_ascii(row_data));
                                     });
 
+                                    let row_address = (row_start * 16 + offset) as u16;
+                                    let row_end = row_address + row_data.len() as u16 - 1;
+                                    let labels: Vec<_> = self
+                                        .annotations
+                                        .labels_overlapping(rom_hash, row_address, row_end)
+                                        .collect();
+                                    ui.label(RichText::new(labels.join(", ")).weak());
+
                                     ui.end_row();
                                 }
                             });
-                        }
-                    });
+                        });
+                }
             });
         });
     }
@@ -282,12 +1646,56 @@ impl epi::App for TemplateApp {
         &mut self,
         _ctx: &egui::Context,
         frame: &epi::Frame,
-        _storage: Option<&dyn epi::Storage>,
+        storage: Option<&dyn epi::Storage>,
     ) {
         frame.set_window_size(Vec2::new(1100.0, 800.0));
+
+        if let Some(storage) = storage {
+            self.settings = epi::get_value(storage, SETTINGS_KEY).unwrap_or_default();
+            self.annotations = epi::get_value(storage, ANNOTATIONS_KEY).unwrap_or_default();
+
+            // A playlist given on the command line takes precedence over the saved one
+            #[cfg(not(target_arch = "wasm32"))]
+            if self.playlist.is_empty() {
+                self.playlist = epi::get_value(storage, PLAYLIST_KEY).unwrap_or_default();
+            }
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn epi::Storage) {
+        epi::set_value(storage, SETTINGS_KEY, &self.settings);
+        epi::set_value(storage, ANNOTATIONS_KEY, &self.annotations);
+        #[cfg(not(target_arch = "wasm32"))]
+        epi::set_value(storage, PLAYLIST_KEY, &self.playlist);
+    }
+
+    fn on_exit(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.save_flags();
     }
 
     fn name(&self) -> &str {
-        "Chippie"
+        APP_NAME
     }
 }
+
+/// Native file dialog for picking a ROM to open
+#[cfg(not(target_arch = "wasm32"))]
+fn rom_file_dialog() -> rfd::FileDialog {
+    rfd::FileDialog::new().add_filter("CHIP-8 ROM", ROM_EXTENSIONS)
+}
+
+/// Native file dialog for picking where to export to, suggesting a file named after the ROM
+#[cfg(not(target_arch = "wasm32"))]
+fn export_file_dialog(
+    rom_path: Option<&PathBuf>,
+    filter: &str,
+    extension: &str,
+) -> rfd::FileDialog {
+    let stem = rom_path
+        .and_then(|path| path.file_stem())
+        .map_or_else(|| "disassembly".into(), |stem| stem.to_string_lossy());
+    rfd::FileDialog::new()
+        .add_filter(filter, &[extension])
+        .set_file_name(&format!("{}.{}", stem, extension))
+}
@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+
+/// Longest frame time in seconds that instructions are executed for, so that a stalled window
+/// doesn't make the next frame execute a huge batch
+pub(crate) const MAX_FRAME_DELTA: f64 = 0.1;
+
+/// Instructions to execute for a frame that took `frame_delta` seconds at `clock_hz`
+///
+/// `budget` carries the fraction of an instruction left over from earlier frames, so that clock
+/// rates that aren't a multiple of the frame rate still average out to the right speed. Returns
+/// the whole instructions to execute and the budget to carry over to the next frame.
+pub(crate) fn cycles_for_frame(budget: f64, frame_delta: f64, clock_hz: u32) -> (u64, f64) {
+    let budget = budget + frame_delta.clamp(0.0, MAX_FRAME_DELTA) * clock_hz as f64;
+    let cycles = budget.floor();
+    (cycles as u64, budget - cycles)
+}
+
+/// Fraction of `clock_hz` achieved by executing `cycles` instructions in `elapsed` seconds
+pub(crate) fn achieved_speed(cycles: u64, elapsed: f64, clock_hz: u32) -> Option<f64> {
+    if elapsed <= 0.0 || clock_hz == 0 {
+        return None;
+    }
+    Some(cycles as f64 / elapsed / clock_hz as f64)
+}
+
+/// Ticks executed, cycles spent and wall clock time taken by each of the most recent frames
+pub(crate) struct FrameHistory {
+    capacity: usize,
+    ticks: VecDeque<u64>,
+    cycles: VecDeque<u64>,
+    times: VecDeque<f64>,
+}
+
+impl FrameHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ticks: VecDeque::with_capacity(capacity),
+            cycles: VecDeque::with_capacity(capacity),
+            times: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Record a frame, forgetting the oldest one once `capacity` frames are kept
+    pub fn record(&mut self, ticks: u64, cycles: u64, frame_time: f64) {
+        if self.ticks.len() == self.capacity {
+            self.ticks.pop_front();
+            self.cycles.pop_front();
+            self.times.pop_front();
+        }
+        self.ticks.push_back(ticks);
+        self.cycles.push_back(cycles);
+        self.times.push_back(frame_time);
+    }
+
+    /// Cycles spent in each recorded frame, oldest first
+    pub fn cycles(&self) -> impl Iterator<Item = u64> + '_ {
+        self.cycles.iter().copied()
+    }
+
+    /// Fraction of `clock_hz` achieved over the recorded frames
+    pub fn achieved_speed(&self, clock_hz: u32) -> Option<f64> {
+        achieved_speed(self.ticks.iter().sum(), self.times.iter().sum(), clock_hz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_history_keeps_the_latest_frames() {
+        let mut history = FrameHistory::new(3);
+        for frame in 1..=5 {
+            history.record(frame, frame * 10, 0.5);
+        }
+        assert_eq!(history.cycles().collect::<Vec<_>>(), [30, 40, 50]);
+    }
+
+    #[test]
+    fn speed_is_measured_in_ticks_not_cycles() {
+        let mut history = FrameHistory::new(4);
+        assert_eq!(history.achieved_speed(100), None);
+
+        // 120 ticks in 1.5 seconds, whatever the cycles cost
+        history.record(40, 400, 0.5);
+        history.record(80, 50, 1.0);
+        assert_eq!(history.achieved_speed(100), Some(0.8));
+        assert_eq!(history.achieved_speed(0), None);
+    }
+
+    #[test]
+    fn one_second_of_frames_executes_the_clock_rate() {
+        for (clock_hz, frame_rate) in [(500, 60), (700, 60), (1000, 144), (540, 50), (60, 60)] {
+            let per_frame = clock_hz as f64 / frame_rate as f64;
+            let mut budget = 0.0;
+            let mut total = 0;
+            for _ in 0..frame_rate {
+                let (cycles, left) = cycles_for_frame(budget, 1.0 / frame_rate as f64, clock_hz);
+                assert!(cycles == per_frame.floor() as u64 || cycles == per_frame.ceil() as u64);
+                assert!((0.0..1.0).contains(&left));
+                budget = left;
+                total += cycles;
+            }
+            // Rounding may leave the last instruction in the budget
+            assert!(total == clock_hz as u64 || total + 1 == clock_hz as u64);
+        }
+    }
+
+    #[test]
+    fn exact_fractions_carry_over_between_frames() {
+        // 15.625 instructions per frame
+        let mut budget = 0.0;
+        let cycles: Vec<u64> = (0..8)
+            .map(|_| {
+                let (cycles, left) = cycles_for_frame(budget, 1.0 / 64.0, 1000);
+                budget = left;
+                cycles
+            })
+            .collect();
+        assert_eq!(cycles, [15, 16, 15, 16, 16, 15, 16, 16]);
+        assert_eq!(cycles.iter().sum::<u64>(), 125);
+        assert_eq!(budget, 0.0);
+    }
+
+    #[test]
+    fn long_and_negative_frames_are_clamped() {
+        assert_eq!(cycles_for_frame(0.0, 2.0, 1000), (100, 0.0));
+        assert_eq!(cycles_for_frame(0.5, -1.0, 1000), (0, 0.5));
+    }
+}
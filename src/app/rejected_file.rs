@@ -0,0 +1,9 @@
+use std::path::PathBuf;
+
+/// File that failed to load as a ROM, kept so that its raw contents can be inspected
+pub(crate) struct RejectedFile {
+    pub path: PathBuf,
+    /// Why the file couldn't be loaded
+    pub error: String,
+    pub contents: Vec<u8>,
+}
@@ -0,0 +1,72 @@
+use crate::app::audio::AudioConfig;
+use crate::app::format::ValueFormat;
+use crate::app::key_repeat::KeyRepeatConfig;
+use crate::interpreter::DisassemblyStyle;
+use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
+
+/// User preferences persisted across sessions
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    /// Display format of register values
+    pub register_format: ValueFormat,
+    /// Width of the screen border in CHIP-8 pixels
+    pub screen_border_width: usize,
+    pub screen_border_color: Color32,
+    /// Color of lit pixels
+    pub screen_foreground: Color32,
+    pub screen_background: Color32,
+    /// Draw the screen at `screen_scale` instead of fitting it to the window
+    pub pin_screen_scale: bool,
+    /// Physical pixels per CHIP-8 pixel when the scale is pinned
+    pub screen_scale: usize,
+    pub flash_border_on_collision: bool,
+    pub reduce_flicker: bool,
+    /// Play a click whenever a draw collides
+    pub click_on_collision: bool,
+    /// Group the memory view into 16-bit words instead of bytes
+    pub memory_word_view: bool,
+    /// Show the words of the memory view little-endian rather than the big-endian CHIP-8 uses
+    pub memory_words_little_endian: bool,
+    /// Execute `ticks_per_frame` instructions every frame instead of following the clock rate
+    pub fixed_ticks_per_frame: bool,
+    pub ticks_per_frame: usize,
+    /// Mnemonic style of the disassembly view
+    pub disassembly_style: DisassemblyStyle,
+    pub audio: AudioConfig,
+    pub key_repeat: KeyRepeatConfig,
+    /// Directory the flag registers of each ROM are persisted in, empty to not persist them
+    pub flag_directory: String,
+    /// Print the interpreter state to stderr if the interpreter panics
+    pub crash_reports: bool,
+    /// Stop running when a `Call` overflows or a `Return` underflows the call stack
+    pub pause_on_stack_error: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            register_format: ValueFormat::Decimal,
+            screen_border_width: 0,
+            screen_border_color: Color32::DARK_GRAY,
+            screen_foreground: Color32::DARK_GREEN,
+            screen_background: Color32::BLACK,
+            pin_screen_scale: false,
+            screen_scale: 4,
+            flash_border_on_collision: false,
+            reduce_flicker: false,
+            click_on_collision: false,
+            memory_word_view: false,
+            memory_words_little_endian: false,
+            fixed_ticks_per_frame: false,
+            ticks_per_frame: 20,
+            disassembly_style: DisassemblyStyle::Verbose,
+            audio: Default::default(),
+            key_repeat: Default::default(),
+            flag_directory: "flags".to_string(),
+            crash_reports: cfg!(debug_assertions),
+            pause_on_stack_error: true,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::interpreter::expr::{EvalError, Expression};
+use crate::interpreter::Chip8InterpreterState;
+
+/// Expression shown in the watch window, optionally pausing emulation once it becomes true
+pub(crate) struct Watch {
+    pub source: String,
+    pub expression: Result<Expression, EvalError>,
+    /// Pause emulation when the expression evaluates to anything but zero
+    pub break_when_true: bool,
+}
+
+impl Watch {
+    pub fn new(source: String) -> Self {
+        let expression = Expression::parse(&source);
+        Self {
+            source,
+            expression,
+            break_when_true: false,
+        }
+    }
+
+    pub fn evaluate(&self, state: &Chip8InterpreterState) -> Result<u32, EvalError> {
+        match &self.expression {
+            Ok(expression) => expression.evaluate(state),
+            Err(e) => Err(e.clone()),
+        }
+    }
+
+    /// Whether emulation should pause, which it also does on errors if `pause_on_error` is set
+    pub fn should_break(&self, state: &Chip8InterpreterState, pause_on_error: bool) -> bool {
+        self.break_when_true
+            && match self.evaluate(state) {
+                Ok(value) => value != 0,
+                Err(_) => pause_on_error,
+            }
+    }
+}
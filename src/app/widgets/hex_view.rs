@@ -0,0 +1,57 @@
+use crate::app::format::{format_ascii, hex_rows};
+use eframe::egui::{self, Response, TextStyle, Ui, Widget};
+
+/// Bytes of one row of a hex view, with a gap after the first eight
+pub(crate) fn hex_bytes(ui: &mut Ui, bytes: &[u8]) {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i == 7 {
+            ui.monospace(format!("{:02x} ", byte));
+        } else {
+            ui.monospace(format!("{:02x}", byte));
+        }
+    }
+}
+
+/// Read-only hex view of a buffer that isn't interpreter memory, like a file that failed to load
+pub(crate) struct HexView<'a> {
+    id: &'a str,
+    bytes: &'a [u8],
+}
+
+impl<'a> HexView<'a> {
+    pub fn new(id: &'a str, bytes: &'a [u8]) -> Self {
+        HexView { id, bytes }
+    }
+}
+
+impl Widget for HexView<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let row_height = ui.text_style_height(&TextStyle::Monospace);
+        let row_count = hex_rows(self.bytes).len();
+        ui.vertical(|ui| {
+            egui::ScrollArea::vertical()
+                .id_source(self.id)
+                .auto_shrink([false, true])
+                .show_rows(ui, row_height, row_count, |ui, visible_rows| {
+                    egui::Grid::new((self.id, "grid"))
+                        .striped(true)
+                        .show(ui, |ui| {
+                            let rows = hex_rows(self.bytes)
+                                .skip(visible_rows.start)
+                                .take(visible_rows.len());
+                            for (offset, row_data) in rows {
+                                // Files can be larger than memory, so offsets get more digits
+                                ui.monospace(format!("{:06x}  ", offset));
+                                ui.horizontal(|ui| hex_bytes(ui, row_data));
+                                ui.horizontal(|ui| {
+                                    ui.monospace(" ");
+                                    ui.monospace(format_ascii(row_data));
+                                });
+                                ui.end_row();
+                            }
+                        });
+                });
+        })
+        .response
+    }
+}
@@ -0,0 +1,108 @@
+use eframe::egui::{self, Response, RichText, Sense, Ui, Widget};
+
+/// Keys in the positions they have on the COSMAC VIP keypad
+const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xc],
+    [0x4, 0x5, 0x6, 0xd],
+    [0x7, 0x8, 0x9, 0xe],
+    [0xa, 0x0, 0xb, 0xf],
+];
+
+/// On screen keypad for devices without a physical keyboard
+///
+/// Keys count as held for as long as the pointer is down on them, the mask of held keys is written
+/// to `held_keys` every frame.
+pub(crate) struct Chip8Keypad<'a> {
+    held_keys: &'a mut u32,
+}
+
+impl<'a> Chip8Keypad<'a> {
+    pub fn new(held_keys: &'a mut u32) -> Self {
+        Chip8Keypad { held_keys }
+    }
+}
+
+impl Widget for Chip8Keypad<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let mut held_keys = 0;
+        let response = egui::Grid::new("keypad")
+            .show(ui, |ui| {
+                for row in KEYPAD_LAYOUT {
+                    for key in row {
+                        let button =
+                            egui::Button::new(RichText::new(format!("{:X}", key)).monospace())
+                                .sense(Sense::click_and_drag());
+                        if ui
+                            .add_sized([40.0, 40.0], button)
+                            .is_pointer_button_down_on()
+                        {
+                            held_keys |= 1u32 << key;
+                        }
+                    }
+                    ui.end_row();
+                }
+            })
+            .response;
+
+        *self.held_keys = held_keys;
+        response
+    }
+}
+
+/// Masks of the keys that went down and the keys that came up going from `previous` to `current`
+pub(crate) fn key_edges(previous: u32, current: u32) -> (u32, u32) {
+    (current & !previous, previous & !current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Chip8Config, Chip8Interpreter};
+
+    #[test]
+    fn edges_report_only_keys_that_changed() {
+        assert_eq!(key_edges(0, 0), (0, 0));
+        assert_eq!(key_edges(0, 1 << 5), (1 << 5, 0));
+        // Key 5 stays held while 7 goes down and 2 comes up
+        assert_eq!(
+            key_edges(1 << 5 | 1 << 2, 1 << 5 | 1 << 7),
+            (1 << 7, 1 << 2)
+        );
+        assert_eq!(key_edges(0xffff, 0), (0, 0xffff));
+    }
+
+    #[test]
+    fn layout_has_every_key_once() {
+        let mask = KEYPAD_LAYOUT
+            .iter()
+            .flatten()
+            .fold(0u32, |mask, &key| mask | 1 << key);
+        assert_eq!(mask, 0xffff);
+    }
+
+    #[test]
+    fn a_tap_completes_wait_for_key_release() {
+        let mut interp = Chip8Interpreter::new();
+        interp.set_config(Chip8Config {
+            wait_for_key_release: true,
+            ..Default::default()
+        });
+        interp.try_load_rom(&[0xf1, 0x0a, 0x12, 0x02]).unwrap();
+
+        // One frame with key 5 held down on the keypad, then one with nothing held
+        for keys in [1 << 5, 0] {
+            let (pressed, released) = key_edges(interp.state().input_keys, keys);
+            for key in 0..16 {
+                if pressed & (1 << key) != 0 {
+                    interp.press_key(key).unwrap();
+                }
+                if released & (1 << key) != 0 {
+                    interp.release_key(key).unwrap();
+                }
+            }
+            interp.tick().unwrap();
+        }
+        assert_eq!(interp.state().registers[1], 5);
+        assert_eq!(interp.state().pc, 0x202);
+    }
+}
@@ -1,5 +1,9 @@
 mod cpu;
+mod hex_view;
+mod keypad;
 mod screen;
 
 pub use cpu::*;
+pub use hex_view::*;
+pub use keypad::*;
 pub use screen::*;
@@ -1,20 +1,171 @@
+use crate::app::flicker::FlickerFilter;
 use crate::interpreter::*;
-use eframe::egui::{Color32, Pos2, Rect, Response, Rounding, Sense, Ui, Vec2, Widget};
+use eframe::egui::{
+    Color32, ColorImage, Mesh, Pos2, Rect, Response, Rounding, Sense, Shape, TextureHandle, Ui,
+    Vec2, Widget,
+};
 
 pub(crate) struct Chip8Screen<'a> {
-    state: &'a Chip8InterpreterState,
+    screen: &'a [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT],
+    /// Border width in CHIP-8 pixels
+    border_width: usize,
+    border_color: Color32,
+    flicker_filter: Option<&'a FlickerFilter>,
+    /// Fixed number of physical pixels per CHIP-8 pixel, `None` to fit the window
+    scale: Option<usize>,
+    /// Color of pixels lit on the first display plane
+    foreground: Color32,
+    background: Color32,
+    /// Whether the screen or the flicker filter may have changed since the last frame, the texture
+    /// is only rebuilt when they did or the way it is drawn changed
+    changed: bool,
 }
 
 impl<'a> Chip8Screen<'a> {
-    pub fn new(state: &'a Chip8InterpreterState) -> Self {
-        Chip8Screen { state }
+    pub fn new(screen: &'a [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT]) -> Self {
+        Chip8Screen {
+            screen,
+            border_width: 0,
+            border_color: Color32::BLACK,
+            flicker_filter: None,
+            scale: None,
+            foreground: PIXEL_ON_COLOR,
+            background: PIXEL_OFF_COLOR,
+            changed: true,
+        }
+    }
+
+    pub fn with_colors(mut self, foreground: Color32, background: Color32) -> Self {
+        self.foreground = foreground;
+        self.background = background;
+        self
+    }
+
+    pub fn with_border(mut self, width: usize, color: Color32) -> Self {
+        self.border_width = width;
+        self.border_color = color;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+
+    pub fn with_flicker_filter(mut self, flicker_filter: &'a FlickerFilter) -> Self {
+        self.flicker_filter = Some(flicker_filter);
+        self
+    }
+
+    /// Keep the texture from the last frame unless `changed` is set, see `screen_dirty`
+    pub fn with_changed(mut self, changed: bool) -> Self {
+        self.changed = changed;
+        self
+    }
+
+    fn color_at(&self, x: usize, y: usize) -> Color32 {
+        let pixel = self.screen[y][x];
+        let held = pixel == 0
+            && self
+                .flicker_filter
+                .map_or(false, |filter| filter.is_held(x, y));
+        if held {
+            self.foreground
+        } else {
+            pixel_color(pixel, self.foreground, self.background)
+        }
+    }
+
+    /// Image of the screen with every CHIP-8 pixel covering `scale` by `scale` image pixels
+    fn color_image(&self, scale: usize) -> ColorImage {
+        let (width, height) = (SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| self.color_at(x / scale, y / scale))
+            .collect();
+        ColorImage {
+            size: [width, height],
+            pixels,
+        }
+    }
+}
+
+/// Texture a screen widget was drawn with, kept in egui memory so that the texture is only
+/// rebuilt and uploaded again when the screen or the way it is drawn changes
+#[derive(Clone)]
+struct ScreenTexture {
+    handle: TextureHandle,
+    /// Image pixels per CHIP-8 pixel
+    scale: usize,
+    foreground: Color32,
+    background: Color32,
+    filtered: bool,
+}
+
+impl ScreenTexture {
+    fn matches(&self, screen: &Chip8Screen<'_>, scale: usize) -> bool {
+        self.scale == scale
+            && self.foreground == screen.foreground
+            && self.background == screen.background
+            && self.filtered == screen.flicker_filter.is_some()
     }
 }
 
+pub(crate) const PIXEL_ON_COLOR: Color32 = Color32::DARK_GREEN;
+pub(crate) const PIXEL_OFF_COLOR: Color32 = Color32::BLACK;
+
+/// Color of pixels lit on the second XO-CHIP display plane only
+const PLANE_2_COLOR: Color32 = Color32::from_rgb(0x99, 0x66, 0x00);
+/// Color of pixels lit on both XO-CHIP display planes
+const BOTH_PLANES_COLOR: Color32 = Color32::from_rgb(0xcc, 0xcc, 0x66);
+
+/// Color of a pixel by the display planes it is lit on, see `Chip8InterpreterState::screen`
+fn pixel_color(pixel: u8, foreground: Color32, background: Color32) -> Color32 {
+    match pixel & ALL_PLANES {
+        0 => background,
+        1 => foreground,
+        2 => PLANE_2_COLOR,
+        _ => BOTH_PLANES_COLOR,
+    }
+}
+
+/// Portion of the window the screen tries to cover when picking a scale
+const SCREEN_FILL_FRACTION: f32 = 0.6;
+
+/// Size in points of a single CHIP-8 pixel
+///
+/// The size is chosen so that every CHIP-8 pixel covers a whole number of physical pixels, which
+/// keeps the display crisp regardless of the display's scale factor.
+pub(crate) fn cell_size(target: Vec2, pixels_per_point: f32) -> f32 {
+    let fit = (target.x / SCREEN_WIDTH as f32).min(target.y / SCREEN_HEIGHT as f32);
+    let physical_pixels = (fit * pixels_per_point).floor().max(1.0);
+    physical_pixels / pixels_per_point
+}
+
+/// Size in points of a single CHIP-8 pixel drawn at a fixed integer `scale`
+///
+/// The scale is reduced as far as needed for the screen to still fit in `target`, but never below
+/// one physical pixel.
+pub(crate) fn scaled_cell_size(scale: usize, target: Vec2, pixels_per_point: f32) -> f32 {
+    let largest = cell_size(target, pixels_per_point) * pixels_per_point;
+    (scale as f32).min(largest).max(1.0) / pixels_per_point
+}
+
 impl Widget for Chip8Screen<'_> {
     fn ui(self, ui: &mut Ui) -> Response {
+        let pixels_per_point = ui.ctx().pixels_per_point();
+        let target = ui.ctx().input().screen_rect().size() * SCREEN_FILL_FRACTION;
+        let cell = match self.scale {
+            Some(scale) => scaled_cell_size(scale, target, pixels_per_point),
+            None => cell_size(target, pixels_per_point),
+        };
+        let border = cell * self.border_width as f32;
+
         let (rect, response) = ui.allocate_exact_size(
-            Vec2::new(640.0, 320.0),
+            Vec2::new(
+                cell * SCREEN_WIDTH as f32 + 2.0 * border,
+                cell * SCREEN_HEIGHT as f32 + 2.0 * border,
+            ),
             Sense {
                 click: false,
                 drag: false,
@@ -23,23 +174,149 @@ impl Widget for Chip8Screen<'_> {
         );
         let painter = ui.painter_at(rect);
 
-        for y in 0..SCREEN_HEIGHT {
-            for x in 0..SCREEN_WIDTH {
-                painter.rect_filled(
-                    Rect::from_min_size(
-                        Pos2::new(rect.left() + 10.0 * x as f32, rect.top() + 10.0 * y as f32),
-                        Vec2::new(10.0, 10.0),
-                    ),
-                    Rounding::none(),
-                    if self.state.screen[y][x] == 0 {
-                        Color32::BLACK
-                    } else {
-                        Color32::DARK_GREEN
-                    },
-                );
-            }
+        // Snap the origin to a physical pixel so cell edges line up with the pixel grid
+        let left = (rect.left() * pixels_per_point).round() / pixels_per_point + border;
+        let top = (rect.top() * pixels_per_point).round() / pixels_per_point + border;
+
+        if self.border_width > 0 {
+            painter.rect_filled(rect, Rounding::none(), self.border_color);
         }
 
+        // Textures are sampled linearly, so the image is uploaded with one texel per physical
+        // pixel to keep the CHIP-8 pixels crisp
+        let scale = (cell * pixels_per_point).round().max(1.0) as usize;
+        let texture_id = ui.id().with("chip8_screen_texture");
+        let cached = ui.memory().data.get_temp::<ScreenTexture>(texture_id);
+        let handle = match cached {
+            Some(cached) if !self.changed && cached.matches(&self, scale) => cached.handle,
+            cached => {
+                let image = self.color_image(scale);
+                let handle = match cached {
+                    Some(mut cached) => {
+                        cached.handle.set(image);
+                        cached.handle
+                    }
+                    None => ui.ctx().load_texture("chip8_screen", image),
+                };
+                let cached = ScreenTexture {
+                    handle: handle.clone(),
+                    scale,
+                    foreground: self.foreground,
+                    background: self.background,
+                    filtered: self.flicker_filter.is_some(),
+                };
+                ui.memory().data.insert_temp(texture_id, cached);
+                handle
+            }
+        };
+
+        let screen_rect = Rect::from_min_size(
+            Pos2::new(left, top),
+            Vec2::new(cell * SCREEN_WIDTH as f32, cell * SCREEN_HEIGHT as f32),
+        );
+        let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+        let mut mesh = Mesh::with_texture(handle.id());
+        mesh.add_rect_with_uv(screen_rect, uv, Color32::WHITE);
+        painter.add(Shape::mesh(mesh));
+
         response
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Render `screen` to RGBA bytes, with every CHIP-8 pixel covering `scale` by `scale` image pixels
+pub(crate) fn render_rgba(
+    screen: &[[u8; SCREEN_WIDTH]; SCREEN_HEIGHT],
+    scale: usize,
+    foreground: Color32,
+    background: Color32,
+) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(SCREEN_WIDTH * SCREEN_HEIGHT * scale * scale * 4);
+    for row in screen {
+        for _ in 0..scale {
+            for &pixel in row {
+                let color = pixel_color(pixel, foreground, background);
+                for _ in 0..scale {
+                    rgba.extend_from_slice(&[color.r(), color.g(), color.b(), color.a()]);
+                }
+            }
+        }
+    }
+    rgba
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_cover_whole_physical_pixels() {
+        // 10 points a cell fit, at 1.5 pixels per point that is 15 physical pixels
+        let target = Vec2::new(640.0, 400.0);
+        assert_eq!(cell_size(target, 1.0), 10.0);
+        assert_eq!(cell_size(target, 1.5) * 1.5, 15.0);
+        // 7.8 points fit, rounded down to 15 physical pixels at 2 pixels per point
+        assert_eq!(cell_size(Vec2::new(500.0, 500.0), 2.0), 7.5);
+    }
+
+    #[test]
+    fn cells_are_never_smaller_than_a_physical_pixel() {
+        assert_eq!(cell_size(Vec2::new(10.0, 10.0), 1.0), 1.0);
+        assert_eq!(cell_size(Vec2::ZERO, 2.0), 0.5);
+    }
+
+    #[test]
+    fn fixed_scale_is_clamped_to_the_target() {
+        let target = Vec2::new(640.0, 320.0);
+        assert_eq!(scaled_cell_size(4, target, 1.0), 4.0);
+        assert_eq!(scaled_cell_size(4, target, 2.0), 2.0);
+        // Too large to fit, so reduced to the 10 pixels that do
+        assert_eq!(scaled_cell_size(20, target, 1.0), 10.0);
+        assert_eq!(scaled_cell_size(0, target, 1.0), 1.0);
+        assert_eq!(scaled_cell_size(4, Vec2::ZERO, 1.0), 1.0);
+    }
+
+    #[test]
+    fn pinned_scales_keep_whole_physical_pixels() {
+        let target = Vec2::new(1280.0, 720.0);
+        for pixels_per_point in [1.0, 1.25, 1.5, 2.0] {
+            for scale in 1..=8 {
+                let physical = scaled_cell_size(scale, target, pixels_per_point) * pixels_per_point;
+                assert_eq!(physical, scale as f32);
+            }
+        }
+        // A small window brings every scale down to what fits, in whole physical pixels
+        let target = Vec2::new(200.0, 100.0);
+        for scale in 4..=8 {
+            assert_eq!(scaled_cell_size(scale, target, 1.25) * 1.25, 3.0);
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn rendered_image_scales_every_pixel() {
+        let mut screen = [[0; SCREEN_WIDTH]; SCREEN_HEIGHT];
+        screen[0][1] = 1;
+        screen[1][0] = 3;
+        let (foreground, background) = (Color32::WHITE, Color32::BLACK);
+        let rgba = render_rgba(&screen, 2, foreground, background);
+        assert_eq!(rgba.len(), SCREEN_WIDTH * 2 * SCREEN_HEIGHT * 2 * 4);
+
+        let color_at = |x: usize, y: usize| {
+            let offset = (y * SCREEN_WIDTH * 2 + x) * 4;
+            Color32::from_rgba_premultiplied(
+                rgba[offset],
+                rgba[offset + 1],
+                rgba[offset + 2],
+                rgba[offset + 3],
+            )
+        };
+        for (x, y) in [(2, 0), (3, 0), (2, 1), (3, 1)] {
+            assert_eq!(color_at(x, y), foreground);
+        }
+        assert_eq!(color_at(0, 2), BOTH_PLANES_COLOR);
+        assert_eq!(color_at(1, 3), BOTH_PLANES_COLOR);
+        assert_eq!(color_at(0, 0), background);
+        assert_eq!(color_at(4, 1), background);
+    }
+}
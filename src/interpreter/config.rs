@@ -0,0 +1,212 @@
+use super::{Chip8InterpreterError, Chip8Variant, CycleCosts, BASE_ADDRESS};
+use serde::{Deserialize, Serialize};
+
+/// Description of a single quirk of `Chip8Config`, see `Chip8Config::quirks`
+#[derive(Clone, Copy, Debug)]
+pub struct QuirkInfo {
+    /// Identifier accepted by `Chip8Config::set_quirk`
+    pub name: &'static str,
+    /// Short human readable description, suitable as a label
+    pub description: &'static str,
+    pub enabled: bool,
+}
+
+struct QuirkField {
+    name: &'static str,
+    description: &'static str,
+    field: fn(&mut Chip8Config) -> &mut bool,
+}
+
+/// Every toggle of `Chip8Config` that changes how programs behave, new ones must be added here
+/// to show up in the UI
+///
+/// Debugging and setup options such as `trap_uninitialized_memory` aren't quirks and are left out.
+const QUIRK_FIELDS: [QuirkField; 7] = [
+    QuirkField {
+        name: "wait_for_key_release",
+        description: "FX0A waits for a key to be released",
+        field: |config| &mut config.wait_for_key_release,
+    },
+    QuirkField {
+        name: "shift_uses_vy",
+        description: "8XY6 and 8XYE shift VY into VX",
+        field: |config| &mut config.quirks.shift_uses_vy,
+    },
+    QuirkField {
+        name: "memory_increments_i",
+        description: "FX55 and FX65 advance I",
+        field: |config| &mut config.quirks.memory_increments_i,
+    },
+    QuirkField {
+        name: "clip_sprites",
+        description: "Clip sprites at the screen edges",
+        field: |config| &mut config.quirks.clip_sprites,
+    },
+    QuirkField {
+        name: "resolution_switch_clears_screen",
+        description: "00FE and 00FF clear the screen",
+        field: |config| &mut config.quirks.resolution_switch_clears_screen,
+    },
+    QuirkField {
+        name: "display_wait",
+        description: "Draws wait for vertical blank",
+        field: |config| &mut config.quirks.display_wait,
+    },
+    QuirkField {
+        name: "display_wait_lowres_only",
+        description: "Draws only wait for vertical blank in low resolution",
+        field: |config| &mut config.quirks.display_wait_lowres_only,
+    },
+];
+
+/// Instruction behaviors that differ between CHIP-8 interpreters
+///
+/// Every quirk is off by default, which is how modern CHIP-8 interpreters behave. Quirks missing
+/// from saved configurations are off as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Quirks {
+    /// Copy VY into VX before shifting, like the COSMAC VIP did, rather than shifting VX in place
+    pub shift_uses_vy: bool,
+    /// Leave I pointing past the last register stored or loaded by FX55 and FX65, like the COSMAC
+    /// VIP did, rather than leaving it unchanged
+    pub memory_increments_i: bool,
+    /// Cut off sprites at the right and bottom edges of the screen instead of wrapping them around,
+    /// the start position of a sprite wraps either way
+    pub clip_sprites: bool,
+    /// Clear the screen when switching between low and high resolution, like XO-CHIP does, rather
+    /// than keeping its contents
+    pub resolution_switch_clears_screen: bool,
+    /// Hold back a draw until the timers have counted down since the previous one, like the
+    /// COSMAC VIP waited for vertical blank, which limits programs to one draw per frame
+    pub display_wait: bool,
+    /// Only apply `display_wait` in low resolution, like SUPER-CHIP does
+    pub display_wait_lowres_only: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Chip8Config {
+    /// Variant of CHIP-8 to emulate
+    pub variant: Chip8Variant,
+    /// Seed used to initialize the random number generator
+    pub seed: u64,
+    /// Reinitialize the random number generator from `seed` whenever the interpreter is reset
+    pub reseed_on_reset: bool,
+    /// Stop with an error when executing a `0000` opcode, which usually means the program ran off
+    /// into uninitialized memory
+    pub trap_uninitialized_memory: bool,
+    /// Make `WaitForKey` complete when a key is released rather than when it is pressed, like the
+    /// COSMAC VIP did
+    #[serde(default)]
+    pub wait_for_key_release: bool,
+    /// Also clear debugging aids like frozen timers, breakpoints and watches on reset, rather than
+    /// keeping them across restarts as debuggers usually do
+    #[serde(default)]
+    pub reset_clears_debug_state: bool,
+    /// Clock speed in instructions per second, `None` uses the default of the selected variant
+    #[serde(default)]
+    pub clock_hz: Option<u32>,
+    /// Address the ROM is loaded at and execution starts from, `None` uses `BASE_ADDRESS`
+    ///
+    /// ETI-660 programs for example start at 0x600.
+    #[serde(default)]
+    pub initial_pc: Option<u16>,
+    /// Instruction behaviors that differ between interpreters
+    #[serde(default)]
+    pub quirks: Quirks,
+    /// Cycles each instruction costs, counted by `Chip8Interpreter::cycles`
+    #[serde(default)]
+    pub cycle_costs: CycleCosts,
+}
+
+impl Chip8Config {
+    /// Effective clock speed in instructions per second
+    pub fn clock_hz(&self) -> u32 {
+        self.clock_hz
+            .unwrap_or_else(|| self.variant.default_clock_hz())
+    }
+
+    /// Effective address the ROM is loaded at and execution starts from
+    pub fn initial_pc(&self) -> u16 {
+        self.initial_pc.unwrap_or(BASE_ADDRESS)
+    }
+
+    /// Name, description and current value of every quirk
+    pub fn quirks(&self) -> Vec<QuirkInfo> {
+        // The field accessors need mutable access, so read through a copy
+        let mut config = *self;
+        QUIRK_FIELDS
+            .iter()
+            .map(|quirk| QuirkInfo {
+                name: quirk.name,
+                description: quirk.description,
+                enabled: *(quirk.field)(&mut config),
+            })
+            .collect()
+    }
+
+    /// Enable or disable the quirk called `name`, as listed by `quirks`
+    pub fn set_quirk(&mut self, name: &str, enabled: bool) -> Result<(), Chip8InterpreterError> {
+        let quirk = QUIRK_FIELDS
+            .iter()
+            .find(|quirk| quirk.name == name)
+            .ok_or_else(|| Chip8InterpreterError::UnknownQuirk(name.to_string()))?;
+        *(quirk.field)(self) = enabled;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quirks_list_every_quirk_field() {
+        let names: Vec<_> = Chip8Config::default()
+            .quirks()
+            .iter()
+            .map(|quirk| quirk.name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "wait_for_key_release",
+                "shift_uses_vy",
+                "memory_increments_i",
+                "clip_sprites",
+                "resolution_switch_clears_screen",
+                "display_wait",
+                "display_wait_lowres_only",
+            ]
+        );
+    }
+
+    #[test]
+    fn quirks_round_trip_by_name() {
+        let mut config = Chip8Config::default();
+        for quirk in Chip8Config::default().quirks() {
+            config.set_quirk(quirk.name, true).unwrap();
+        }
+        assert!(config.quirks().iter().all(|quirk| quirk.enabled));
+        assert!(config.wait_for_key_release);
+        assert!(config.quirks.display_wait_lowres_only);
+
+        config.set_quirk("clip_sprites", false).unwrap();
+        assert!(!config.quirks.clip_sprites);
+    }
+
+    #[test]
+    fn debugging_options_are_not_quirks() {
+        let mut config = Chip8Config::default();
+        for name in [
+            "reseed_on_reset",
+            "trap_uninitialized_memory",
+            "reset_clears_debug_state",
+        ] {
+            assert!(matches!(
+                config.set_quirk(name, true),
+                Err(Chip8InterpreterError::UnknownQuirk(_))
+            ));
+        }
+    }
+}
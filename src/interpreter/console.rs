@@ -0,0 +1,350 @@
+use super::{Chip8Interpreter, Chip8InterpreterError, SCREEN_HEIGHT, SCREEN_WIDTH};
+use std::fmt::Write;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Chip8ConsoleError {
+    #[error("Unknown command")]
+    UnknownCommand(String),
+    #[error("Missing argument")]
+    MissingArgument,
+    #[error("Invalid argument")]
+    InvalidArgument(String),
+    #[error("Interpreter error")]
+    Interpreter(#[from] Chip8InterpreterError),
+}
+
+/// Text command interface to the interpreter
+///
+/// Supported commands:
+///
+/// * `step [n]` - execute n instructions (default 1), ignoring breakpoints
+/// * `run [n]` - execute up to n instructions (default 1000), stopping at breakpoints
+/// * `out [n]` - execute up to n instructions (default 1000) until the current subroutine returns
+/// * `reg VX = NN` - set register X to NN
+/// * `mem NNNN = NN` - set the byte at address NNNN to NN
+/// * `bp NNNN` - toggle a breakpoint at address NNNN
+/// * `wp NNNN` - toggle a watchpoint on writes to address NNNN
+/// * `dis NNNN NNNN` - disassemble the given address range
+/// * `screen` - print the screen contents
+///
+/// Addresses and values are hexadecimal with an optional `0x` prefix, counts are decimal.
+#[derive(Default)]
+pub struct Chip8Console {}
+
+const DEFAULT_RUN_LENGTH: usize = 1000;
+
+fn parse_hex(text: &str) -> Result<u16, Chip8ConsoleError> {
+    let digits = text.trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|_| Chip8ConsoleError::InvalidArgument(text.into()))
+}
+
+fn parse_count(text: Option<&str>, default: usize) -> Result<usize, Chip8ConsoleError> {
+    match text {
+        Some(text) => text
+            .parse()
+            .map_err(|_| Chip8ConsoleError::InvalidArgument(text.into())),
+        None => Ok(default),
+    }
+}
+
+fn parse_assignment<'a>(
+    args: &mut impl Iterator<Item = &'a str>,
+) -> Result<(&'a str, &'a str), Chip8ConsoleError> {
+    let target = args.next().ok_or(Chip8ConsoleError::MissingArgument)?;
+    match args.next() {
+        Some("=") => {}
+        Some(other) => return Err(Chip8ConsoleError::InvalidArgument(other.into())),
+        None => return Err(Chip8ConsoleError::MissingArgument),
+    }
+    let value = args.next().ok_or(Chip8ConsoleError::MissingArgument)?;
+    Ok((target, value))
+}
+
+fn parse_byte(text: &str) -> Result<u8, Chip8ConsoleError> {
+    let value = parse_hex(text)?;
+    u8::try_from(value).map_err(|_| Chip8ConsoleError::InvalidArgument(text.into()))
+}
+
+impl Chip8Console {
+    /// Execute a single command line, returning the text to display
+    pub fn execute(
+        &mut self,
+        interpreter: &mut Chip8Interpreter,
+        line: &str,
+    ) -> Result<String, Chip8ConsoleError> {
+        let mut args = line.split_whitespace();
+        let command = match args.next() {
+            Some(command) => command,
+            None => return Ok(String::new()),
+        };
+
+        match command {
+            "step" => {
+                let count = parse_count(args.next(), 1)?;
+                for _ in 0..count {
+                    match interpreter.tick() {
+                        Err(Chip8InterpreterError::BreakpointHit(_)) => interpreter.tick()?,
+                        result => result?,
+                    }
+                }
+                Ok(format!("PC: {:04x}", interpreter.state().pc))
+            }
+            "run" => {
+                let count = parse_count(args.next(), DEFAULT_RUN_LENGTH)?;
+                for _ in 0..count {
+                    match interpreter.tick() {
+                        Err(Chip8InterpreterError::BreakpointHit(address)) => {
+                            return Ok(format!("Breakpoint hit at {:04x}", address));
+                        }
+                        Err(Chip8InterpreterError::WatchpointHit { address, value }) => {
+                            return Ok(format!(
+                                "Watchpoint hit, {:02x} written to {:04x}",
+                                value, address
+                            ));
+                        }
+                        result => result?,
+                    }
+                    if interpreter.is_halted() {
+                        return Ok("Program exited".to_string());
+                    }
+                }
+                Ok(format!("PC: {:04x}", interpreter.state().pc))
+            }
+            "out" => {
+                let count = parse_count(args.next(), DEFAULT_RUN_LENGTH)?;
+                interpreter.step_out(count)?;
+                Ok(format!("PC: {:04x}", interpreter.state().pc))
+            }
+            "reg" => {
+                let (register, value) = parse_assignment(&mut args)?;
+                let index = register
+                    .strip_prefix(['V', 'v'])
+                    .and_then(|index| usize::from_str_radix(index, 16).ok())
+                    .ok_or_else(|| Chip8ConsoleError::InvalidArgument(register.into()))?;
+                let value = parse_byte(value)?;
+                interpreter.set_register(index, value)?;
+                Ok(format!("V{:x} = {:02x}", index, value))
+            }
+            "mem" => {
+                let (address, value) = parse_assignment(&mut args)?;
+                let address = parse_hex(address)?;
+                let value = parse_byte(value)?;
+                interpreter.write_memory(address, value)?;
+                Ok(format!("{:04x} = {:02x}", address, value))
+            }
+            "bp" => {
+                let address = parse_hex(args.next().ok_or(Chip8ConsoleError::MissingArgument)?)?;
+                if interpreter.remove_breakpoint(address) {
+                    Ok(format!("Breakpoint removed at {:04x}", address))
+                } else {
+                    interpreter.add_breakpoint(address);
+                    Ok(format!("Breakpoint set at {:04x}", address))
+                }
+            }
+            "wp" => {
+                let address = parse_hex(args.next().ok_or(Chip8ConsoleError::MissingArgument)?)?;
+                if interpreter.remove_watchpoint(address) {
+                    Ok(format!("Watchpoint removed at {:04x}", address))
+                } else {
+                    interpreter.add_watchpoint(address);
+                    Ok(format!("Watchpoint set at {:04x}", address))
+                }
+            }
+            "dis" => {
+                let start = parse_hex(args.next().ok_or(Chip8ConsoleError::MissingArgument)?)?;
+                let end = parse_hex(args.next().ok_or(Chip8ConsoleError::MissingArgument)?)?;
+                let mut output = String::new();
+                for decoded in interpreter.disassemble_range(start, end) {
+                    match decoded.instruction {
+                        Ok(instruction) => {
+                            writeln!(output, "{:04x}:  {}", decoded.address, instruction)
+                        }
+                        Err(_) => writeln!(output, "{:04x}:", decoded.address),
+                    }
+                    .unwrap();
+                }
+                Ok(output)
+            }
+            "screen" => {
+                let mut output = String::with_capacity((SCREEN_WIDTH + 1) * SCREEN_HEIGHT);
+                for row in interpreter.state().screen.iter() {
+                    output.extend(row.iter().map(|&pixel| if pixel == 0 { '.' } else { '#' }));
+                    output.push('\n');
+                }
+                Ok(output)
+            }
+            _ => Err(Chip8ConsoleError::UnknownCommand(command.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Chip8Config, Chip8Instruction, Chip8Variant};
+
+    fn interpreter(rom: &[u8]) -> Chip8Interpreter {
+        let mut interp = Chip8Interpreter::new();
+        interp.set_config(Chip8Config {
+            variant: Chip8Variant::SuperChip,
+            ..Default::default()
+        });
+        interp.try_load_rom(rom).unwrap();
+        interp
+    }
+
+    #[test]
+    fn step_ignores_breakpoints() {
+        let mut interp = interpreter(&[0x60, 0x01, 0x61, 0x02, 0x12, 0x04]);
+        let mut console = Chip8Console::default();
+        interp.add_breakpoint(0x202);
+
+        assert_eq!(console.execute(&mut interp, "step").unwrap(), "PC: 0202");
+        assert_eq!(console.execute(&mut interp, "step 2").unwrap(), "PC: 0204");
+        assert_eq!(interp.state().registers[1], 2);
+    }
+
+    #[test]
+    fn run_stops_at_breakpoints_and_exit() {
+        let mut interp = interpreter(&[0x60, 0x01, 0x61, 0x02, 0x00, 0xfd]);
+        let mut console = Chip8Console::default();
+        interp.add_breakpoint(0x202);
+
+        assert_eq!(console.execute(&mut interp, "run 1").unwrap(), "PC: 0202");
+        assert_eq!(
+            console.execute(&mut interp, "run").unwrap(),
+            "Breakpoint hit at 0202"
+        );
+        assert_eq!(
+            console.execute(&mut interp, "run").unwrap(),
+            "Program exited"
+        );
+        assert!(interp.is_halted());
+    }
+
+    #[test]
+    fn run_stops_at_watchpoints() {
+        // Store the digits of V0 at 0x300
+        let mut interp = interpreter(&[0x60, 0x7b, 0xa3, 0x00, 0xf0, 0x33, 0x12, 0x06]);
+        let mut console = Chip8Console::default();
+
+        assert_eq!(
+            console.execute(&mut interp, "wp 0x300").unwrap(),
+            "Watchpoint set at 0300"
+        );
+        assert_eq!(
+            console.execute(&mut interp, "run").unwrap(),
+            "Watchpoint hit, 01 written to 0300"
+        );
+        assert_eq!(
+            console.execute(&mut interp, "wp 300").unwrap(),
+            "Watchpoint removed at 0300"
+        );
+    }
+
+    #[test]
+    fn out_returns_from_subroutine() {
+        let mut interp = interpreter(&[0x22, 0x04, 0x12, 0x02, 0x60, 0x05, 0x00, 0xee]);
+        let mut console = Chip8Console::default();
+        interp.tick().unwrap();
+
+        assert_eq!(console.execute(&mut interp, "out").unwrap(), "PC: 0202");
+        assert_eq!(interp.state().registers[0], 5);
+    }
+
+    #[test]
+    fn reg_and_mem_assign_values() {
+        let mut interp = interpreter(&[]);
+        let mut console = Chip8Console::default();
+
+        assert_eq!(
+            console.execute(&mut interp, "reg V3 = 2a").unwrap(),
+            "V3 = 2a"
+        );
+        assert_eq!(interp.state().registers[3], 0x2a);
+        assert_eq!(
+            console.execute(&mut interp, "mem 0x300 = ff").unwrap(),
+            "0300 = ff"
+        );
+        assert_eq!(interp.state().memory[0x300], 0xff);
+
+        assert!(matches!(
+            console.execute(&mut interp, "reg Vg = 1"),
+            Err(Chip8ConsoleError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            console.execute(&mut interp, "reg V3 2a"),
+            Err(Chip8ConsoleError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            console.execute(&mut interp, "mem 300 = 100"),
+            Err(Chip8ConsoleError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            console.execute(&mut interp, "mem 300 ="),
+            Err(Chip8ConsoleError::MissingArgument)
+        ));
+    }
+
+    #[test]
+    fn bp_toggles_breakpoints() {
+        let mut interp = interpreter(&[]);
+        let mut console = Chip8Console::default();
+
+        assert_eq!(
+            console.execute(&mut interp, "bp 204").unwrap(),
+            "Breakpoint set at 0204"
+        );
+        assert_eq!(
+            console.execute(&mut interp, "bp 0x204").unwrap(),
+            "Breakpoint removed at 0204"
+        );
+        assert!(matches!(
+            console.execute(&mut interp, "bp"),
+            Err(Chip8ConsoleError::MissingArgument)
+        ));
+    }
+
+    #[test]
+    fn dis_lists_each_address_of_the_range() {
+        let mut interp = interpreter(&[0x60, 0x01, 0x12, 0x02]);
+        let mut console = Chip8Console::default();
+
+        let output = console.execute(&mut interp, "dis 200 204").unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("0200:  "));
+        assert_eq!(
+            lines[1],
+            format!("0202:  {}", Chip8Instruction::Jump { address: 0x202 })
+        );
+    }
+
+    #[test]
+    fn screen_prints_every_pixel() {
+        // Draw the font sprite for 0 in the top left corner
+        let mut interp = interpreter(&[0xa0, 0x00, 0xd0, 0x05]);
+        let mut console = Chip8Console::default();
+        interp.run_cycles(2).unwrap();
+
+        let output = console.execute(&mut interp, "screen").unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), SCREEN_HEIGHT);
+        assert!(lines.iter().all(|line| line.len() == SCREEN_WIDTH));
+        assert!(lines[0].starts_with("####...."));
+        assert!(lines[1].starts_with("#..#...."));
+    }
+
+    #[test]
+    fn empty_and_unknown_commands() {
+        let mut interp = interpreter(&[]);
+        let mut console = Chip8Console::default();
+
+        assert_eq!(console.execute(&mut interp, "  ").unwrap(), "");
+        assert!(matches!(
+            console.execute(&mut interp, "jump 200"),
+            Err(Chip8ConsoleError::UnknownCommand(_))
+        ));
+    }
+}
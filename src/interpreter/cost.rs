@@ -0,0 +1,96 @@
+use super::Chip8Instruction;
+use serde::{Deserialize, Serialize};
+
+/// Cycles executing an instruction costs, used to show how much work a program does each frame
+///
+/// Every instruction costs `base` cycles. Draws cost `per_sprite_row` more for every row of the
+/// sprite, and instructions moving registers to or from memory or the flags cost `per_register`
+/// more for every register. The defaults make every instruction cost a single cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CycleCosts {
+    pub base: u32,
+    pub per_sprite_row: u32,
+    pub per_register: u32,
+}
+
+impl Default for CycleCosts {
+    fn default() -> Self {
+        Self {
+            base: 1,
+            per_sprite_row: 0,
+            per_register: 0,
+        }
+    }
+}
+
+impl CycleCosts {
+    /// Cycles executing `instruction` costs
+    pub fn cost(&self, instruction: &Chip8Instruction) -> u32 {
+        let extra = match *instruction {
+            // A length of 0 draws a 16 row sprite where SUPER-CHIP supports it
+            Chip8Instruction::Draw { len, .. } => {
+                self.per_sprite_row * if len == 0 { 16 } else { len as u32 }
+            }
+            Chip8Instruction::StoreRegisters { count }
+            | Chip8Instruction::LoadRegisters { count }
+            | Chip8Instruction::StoreFlags { count }
+            | Chip8Instruction::LoadFlags { count } => self.per_register * count as u32,
+            Chip8Instruction::StoreRegisterRange { x, y }
+            | Chip8Instruction::LoadRegisterRange { x, y } => {
+                self.per_register * (x.abs_diff(y) + 1) as u32
+            }
+            _ => 0,
+        };
+        self.base + extra
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_costs_one_cycle_each() {
+        let costs = CycleCosts::default();
+        assert_eq!(costs.cost(&Chip8Instruction::ClearScreen), 1);
+        assert_eq!(
+            costs.cost(&Chip8Instruction::Draw {
+                x: 0,
+                y: 1,
+                len: 15
+            }),
+            1
+        );
+        assert_eq!(
+            costs.cost(&Chip8Instruction::StoreRegisters { count: 16 }),
+            1
+        );
+    }
+
+    #[test]
+    fn rows_and_registers_add_to_the_base_cost() {
+        let costs = CycleCosts {
+            base: 2,
+            per_sprite_row: 3,
+            per_register: 5,
+        };
+        assert_eq!(costs.cost(&Chip8Instruction::Return), 2);
+        assert_eq!(
+            costs.cost(&Chip8Instruction::Draw { x: 0, y: 1, len: 4 }),
+            14
+        );
+        assert_eq!(
+            costs.cost(&Chip8Instruction::Draw { x: 0, y: 1, len: 0 }),
+            50
+        );
+        assert_eq!(
+            costs.cost(&Chip8Instruction::LoadRegisters { count: 3 }),
+            17
+        );
+        assert_eq!(
+            costs.cost(&Chip8Instruction::StoreRegisterRange { x: 5, y: 2 }),
+            22
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use super::Chip8Interpreter;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+
+impl Chip8Interpreter {
+    /// Human readable dump of the machine state, meant to be attached to bug reports
+    ///
+    /// Lists PC, I, the timers, the registers, the call stack, the last instruction executed and
+    /// the PCs of the states kept for `step_back`, oldest first.
+    pub fn crash_report(&self) -> String {
+        let state = &self.state;
+        let mut report = String::new();
+        writeln!(report, "Interpreter state at tick {}", self.ticks).unwrap();
+        writeln!(
+            report,
+            "PC: {:04x}  I: {:04x}  DT: {:02x}  ST: {:02x}",
+            state.pc, state.i, state.dt, state.st
+        )
+        .unwrap();
+
+        write!(report, "V0-VF:").unwrap();
+        for value in state.registers {
+            write!(report, " {:02x}", value).unwrap();
+        }
+        writeln!(report).unwrap();
+
+        // The stack pointer itself may be what went wrong
+        write!(report, "Stack (SP {}):", state.sp).unwrap();
+        for address in state.stack.get(..state.sp).unwrap_or(&state.stack) {
+            write!(report, " {:04x}", address).unwrap();
+        }
+        writeln!(report).unwrap();
+
+        match self.last_instruction {
+            Some(instruction) => writeln!(report, "Last instruction: {}", instruction),
+            None => writeln!(report, "Last instruction: none"),
+        }
+        .unwrap();
+
+        write!(report, "Recent PCs:").unwrap();
+        for previous in &self.history {
+            write!(report, " {:04x}", previous.pc).unwrap();
+        }
+        if self.history.is_empty() {
+            write!(report, " none, enable the rewind history to keep them").unwrap();
+        }
+        writeln!(report).unwrap();
+
+        report
+    }
+}
+
+/// Run `f`, printing the crash report of the interpreter to stderr if it panics
+///
+/// The panic carries on unwinding after the report is printed. Errors returned by `f` pass through
+/// untouched, only panics are reported.
+pub fn with_crash_report<T>(
+    interpreter: &mut Chip8Interpreter,
+    f: impl FnOnce(&mut Chip8Interpreter) -> T,
+) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(|| f(interpreter))) {
+        Ok(result) => result,
+        Err(payload) => {
+            eprintln!("{}", interpreter.crash_report());
+            panic::resume_unwind(payload)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Chip8Instruction, Chip8InterpreterError};
+
+    #[test]
+    fn report_lists_the_machine_state() {
+        // VA := 2B, I := 300, then call a subroutine that spins
+        let mut interp = Chip8Interpreter::new();
+        interp.set_history_depth(8);
+        interp
+            .try_load_rom(&[0x6a, 0x2b, 0xa3, 0x00, 0x22, 0x08, 0x12, 0x06, 0x12, 0x08])
+            .unwrap();
+        interp.run_cycles(3).unwrap();
+
+        let report = interp.crash_report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "Interpreter state at tick 3",
+                "PC: 0208  I: 0300  DT: 00  ST: 00",
+                "V0-VF: 00 00 00 00 00 00 00 00 00 00 2b 00 00 00 00 00",
+                "Stack (SP 1): 0206",
+                &format!(
+                    "Last instruction: {}",
+                    Chip8Instruction::Call { address: 0x208 }
+                ),
+                "Recent PCs: 0200 0202 0204",
+            ]
+        );
+    }
+
+    #[test]
+    fn report_without_history_says_how_to_get_it() {
+        let interp = Chip8Interpreter::new();
+        let report = interp.crash_report();
+        assert!(report.contains("Last instruction: none\n"));
+        assert!(report.contains("Recent PCs: none, enable the rewind history"));
+    }
+
+    #[test]
+    fn errors_pass_through_the_crash_report() {
+        let mut interp = Chip8Interpreter::new();
+        interp.try_load_rom(&[0x00, 0xee]).unwrap();
+        let result = with_crash_report(&mut interp, |interp| interp.tick());
+        assert!(matches!(
+            result,
+            Err(Chip8InterpreterError::CallStackEmpty { pc: 0x200 })
+        ));
+    }
+}
@@ -0,0 +1,55 @@
+use super::{Chip8Interpreter, DecodedInstruction};
+use std::collections::HashMap;
+
+/// Remembers decoded instructions until the interpreter memory changes
+///
+/// Views that decode the same addresses every frame, like the disassembly, can go through the
+/// cache so that nothing is decoded again while execution is paused.
+#[derive(Default)]
+pub struct DecodeCache {
+    /// Memory generation the entries were decoded from
+    generation: Option<u64>,
+    entries: HashMap<u16, DecodedInstruction>,
+}
+
+impl DecodeCache {
+    pub fn get(&mut self, interpreter: &Chip8Interpreter, address: u16) -> &DecodedInstruction {
+        let generation = interpreter.memory_generation();
+        if self.generation != Some(generation) {
+            self.entries.clear();
+            self.generation = Some(generation);
+        }
+
+        self.entries
+            .entry(address)
+            .or_insert_with(|| interpreter.decode_at(address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_writes_invalidate_cached_decodes() {
+        let mut interp = Chip8Interpreter::new();
+        interp.try_load_rom(&[0x00, 0xe0, 0x12, 0x02]).unwrap();
+        let mut cache = DecodeCache::default();
+        assert_eq!(cache.get(&interp, 0x200).opcode, 0x00e0);
+
+        // Running without writing memory keeps the cached entries
+        interp.tick().unwrap();
+        let generation = cache.generation;
+        assert_eq!(cache.get(&interp, 0x200).opcode, 0x00e0);
+        assert_eq!(cache.generation, generation);
+
+        interp.write_memory(0x201, 0xee).unwrap();
+        assert_eq!(cache.get(&interp, 0x200).opcode, 0x00ee);
+
+        interp.try_load_rom(&[0x60, 0x01]).unwrap();
+        assert_eq!(cache.get(&interp, 0x200).opcode, 0x6001);
+
+        interp.reset();
+        assert_eq!(cache.get(&interp, 0x200).opcode, 0x0000);
+    }
+}
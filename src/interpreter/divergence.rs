@@ -0,0 +1,122 @@
+use super::rng;
+use super::{Chip8Config, Chip8Interpreter, Chip8InterpreterError, DecodedInstruction};
+
+/// First point at which two interpreters running the same ROM stopped agreeing
+#[derive(Debug)]
+pub struct Divergence {
+    /// Cycle on which the diverging instruction was executed
+    pub cycle: u64,
+    /// Instruction executed on that cycle, as decoded under the first configuration
+    pub instruction: DecodedInstruction,
+    /// Names of the parts of the state that differ afterwards
+    pub differences: Vec<&'static str>,
+}
+
+/// Run `rom` under both configurations in lockstep and report the first instruction after which
+/// the interpreter states differ
+///
+/// Returns `None` if the states still agree after `max_cycles` cycles, and an error if both
+/// interpreters fail on the same cycle. Each interpreter is seeded from its own configuration so
+/// that `Random` only diverges when the seeds do.
+pub fn trace_divergence(
+    rom: &[u8],
+    left: Chip8Config,
+    right: Chip8Config,
+    max_cycles: u64,
+) -> Result<Option<Divergence>, Chip8InterpreterError> {
+    let mut left = start(rom, left)?;
+    let mut right = start(rom, right)?;
+
+    for _ in 0..max_cycles {
+        let cycle = left.ticks;
+        let instruction = left.decode_at(left.state.pc);
+
+        let failed = match (left.tick(), right.tick()) {
+            (Err(e), Err(_)) => return Err(e),
+            (left_result, right_result) => left_result.is_err() != right_result.is_err(),
+        };
+
+        let mut differences = differences(&left, &right);
+        if failed {
+            differences.push("error");
+        }
+        if !differences.is_empty() {
+            return Ok(Some(Divergence {
+                cycle,
+                instruction,
+                differences,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+fn start(rom: &[u8], config: Chip8Config) -> Result<Chip8Interpreter, Chip8InterpreterError> {
+    let mut interpreter = Chip8Interpreter::new();
+    interpreter.set_config(config);
+    interpreter.reset();
+    interpreter.rng = rng::seeded_rng(config.seed);
+    interpreter.try_load_rom(rom)?;
+    Ok(interpreter)
+}
+
+fn differences(left: &Chip8Interpreter, right: &Chip8Interpreter) -> Vec<&'static str> {
+    let (left, right) = (&left.state, &right.state);
+    [
+        ("registers", left.registers != right.registers),
+        ("stack", left.stack != right.stack || left.sp != right.sp),
+        ("memory", left.memory != right.memory),
+        ("screen", left.screen != right.screen),
+        ("i", left.i != right.i),
+        ("pc", left.pc != right.pc),
+        ("timers", left.dt != right.dt || left.st != right.st),
+    ]
+    .into_iter()
+    .filter(|(_, differs)| *differs)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Chip8Instruction, Quirks};
+
+    fn with_shift_quirk(shift_uses_vy: bool) -> Chip8Config {
+        Chip8Config {
+            quirks: Quirks {
+                shift_uses_vy,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shift_quirk_diverges_on_the_shift() {
+        // V0 := 8, V1 := 4, V0 >>= V1, then spin
+        let rom = [0x60, 0x08, 0x61, 0x04, 0x80, 0x16, 0x12, 0x06];
+        let divergence =
+            trace_divergence(&rom, with_shift_quirk(false), with_shift_quirk(true), 100)
+                .unwrap()
+                .unwrap();
+
+        assert_eq!(divergence.cycle, 2);
+        assert_eq!(divergence.instruction.address, 0x204);
+        assert!(matches!(
+            divergence.instruction.instruction,
+            Ok(Chip8Instruction::ShiftRight { x: 0, y: 1 })
+        ));
+        assert_eq!(divergence.differences, ["registers"]);
+    }
+
+    #[test]
+    fn identical_configurations_never_diverge() {
+        let rom = [0x60, 0x08, 0x61, 0x04, 0x80, 0x16, 0x12, 0x06];
+        let config = with_shift_quirk(true);
+        assert!(trace_divergence(&rom, config, config, 100)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -4,18 +4,42 @@ use thiserror::Error;
 pub enum Chip8InterpreterError {
     #[error("ROM file is too large to load")]
     RomFileTooLarge,
+    #[error("Initial PC {0:04x} is odd or outside of memory")]
+    InvalidInitialPc(u16),
     #[error("Invalid instruction")]
     InvalidInstruction(u16),
     #[error("Program counter out of bounds")]
     ProgramCounterOutOfBounds(u16),
-    #[error("Call stack depth exceeded")]
-    CallStackDepthExceeded,
-    #[error("Call stack is empty")]
-    CallStackEmpty,
+    #[error("Program counter {0:04x} is odd")]
+    UnalignedProgramCounter(u16),
+    #[error("Call stack depth of {depth} exceeded by call at {pc:04x}")]
+    CallStackDepthExceeded { pc: u16, depth: usize },
+    #[error("Return at {pc:04x} with an empty call stack")]
+    CallStackEmpty { pc: u16 },
+    #[error("Not in a subroutine at {pc:04x}")]
+    NotInSubroutine { pc: u16 },
+    #[error("No earlier state in the history")]
+    HistoryEmpty,
+    #[error("Breakpoint hit at {0:04x}")]
+    BreakpointHit(u16),
+    #[error("Watchpoint hit, {value:02x} written to {address:04x}")]
+    WatchpointHit { address: u16, value: u8 },
     #[error("Memory access error")]
     MemoryAccessError,
     #[error("Invalid input key")]
     InvalidInputKey(u8),
     #[error("Expecting input key")]
     ExpectingInputKey,
+    #[error("Invalid register")]
+    InvalidRegister(usize),
+    #[error("Executing uninitialized memory at {0:04x}")]
+    UninitializedMemory(u16),
+    #[error("Execution limit reached after {cycles} cycles")]
+    ExecutionLimitReached { cycles: u64 },
+    #[error("Waiting for a key at {pc:04x}")]
+    WaitingForKey { pc: u16 },
+    #[error("Unsupported save state version {0}")]
+    UnsupportedSaveVersion(u32),
+    #[error("Unknown quirk {0}")]
+    UnknownQuirk(String),
 }
@@ -0,0 +1,23 @@
+/// Notable things that happened while executing an instruction
+///
+/// The interpreter collects these until they are drained, see `Chip8Interpreter::take_events`, so
+/// that callers can react to them without inspecting the interpreter state themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Event {
+    /// A draw turned off at least one pixel
+    Collision,
+    /// The screen was cleared
+    ScreenCleared,
+    /// The sound timer went from zero to non-zero
+    SoundStarted,
+    /// The sound timer reached zero, either by counting down or by being set
+    SoundStopped,
+    /// The opcode at `address` could not be decoded
+    InvalidOpcode { address: u16, opcode: u16 },
+    /// Execution stopped at a breakpoint at the given address
+    BreakpointHit(u16),
+    /// The `Call` at the given address found the call stack full
+    StackOverflow(u16),
+    /// The `Return` at the given address found the call stack empty
+    StackUnderflow(u16),
+}
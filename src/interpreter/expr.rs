@@ -0,0 +1,250 @@
+use super::Chip8InterpreterState;
+use thiserror::Error;
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum EvalError {
+    #[error("Syntax error: {0}")]
+    Syntax(String),
+    #[error("Register V{0:X} doesn't exist")]
+    InvalidRegister(usize),
+    #[error("Address {0:04x} is outside of memory")]
+    AddressOutOfRange(u32),
+}
+
+/// Value read from the interpreter state or written literally in the expression
+#[derive(Clone, Debug)]
+enum Operand {
+    Literal(u32),
+    Register(usize),
+    /// Byte of memory at the address given by the inner operand
+    Memory(Box<Operand>),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// Expression over the interpreter state, used by watches and conditional breakpoints
+///
+/// An expression is either a single value or two values compared with one of `==`, `!=`, `<`,
+/// `<=`, `>` or `>=`. Values are registers `v0` to `vf`, the special registers `i`, `pc`, `sp`,
+/// `dt` and `st`, a byte of memory `[address]`, or a decimal or `0x` prefixed hexadecimal literal.
+/// Comparisons evaluate to 1 when true and 0 otherwise.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    lhs: Operand,
+    comparison: Option<(Comparison, Operand)>,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, EvalError> {
+        let mut parser = Parser { input: source };
+        let lhs = parser.operand()?;
+        let comparison = if parser.at_end() {
+            None
+        } else {
+            Some((parser.comparison()?, parser.operand()?))
+        };
+
+        if !parser.at_end() {
+            return Err(EvalError::Syntax(format!(
+                "unexpected `{}`",
+                parser.input.trim()
+            )));
+        }
+
+        Ok(Expression { lhs, comparison })
+    }
+
+    /// Evaluate against `state`, failing if the expression refers to state that doesn't exist
+    pub fn evaluate(&self, state: &Chip8InterpreterState) -> Result<u32, EvalError> {
+        let lhs = self.lhs.evaluate(state)?;
+        match &self.comparison {
+            None => Ok(lhs),
+            Some((comparison, rhs)) => {
+                let rhs = rhs.evaluate(state)?;
+                let result = match comparison {
+                    Comparison::Equal => lhs == rhs,
+                    Comparison::NotEqual => lhs != rhs,
+                    Comparison::Less => lhs < rhs,
+                    Comparison::LessOrEqual => lhs <= rhs,
+                    Comparison::Greater => lhs > rhs,
+                    Comparison::GreaterOrEqual => lhs >= rhs,
+                };
+                Ok(result as u32)
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn evaluate(&self, state: &Chip8InterpreterState) -> Result<u32, EvalError> {
+        match self {
+            Operand::Literal(value) => Ok(*value),
+            Operand::Register(index) => state
+                .registers
+                .get(*index)
+                .map(|&value| value as u32)
+                .ok_or(EvalError::InvalidRegister(*index)),
+            Operand::Memory(address) => {
+                let address = address.evaluate(state)?;
+                state
+                    .memory
+                    .get(address as usize)
+                    .map(|&value| value as u32)
+                    .ok_or(EvalError::AddressOutOfRange(address))
+            }
+            Operand::I => Ok(state.i as u32),
+            Operand::Pc => Ok(state.pc as u32),
+            Operand::Sp => Ok(state.sp as u32),
+            Operand::Dt => Ok(state.dt as u32),
+            Operand::St => Ok(state.st as u32),
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+}
+
+impl Parser<'_> {
+    fn at_end(&mut self) -> bool {
+        self.input = self.input.trim_start();
+        self.input.is_empty()
+    }
+
+    fn operand(&mut self) -> Result<Operand, EvalError> {
+        self.input = self.input.trim_start();
+        if let Some(rest) = self.input.strip_prefix('[') {
+            self.input = rest;
+            let address = self.operand()?;
+            self.input = self.input.trim_start();
+            self.input = self
+                .input
+                .strip_prefix(']')
+                .ok_or_else(|| EvalError::Syntax("missing `]`".to_string()))?;
+            return Ok(Operand::Memory(Box::new(address)));
+        }
+
+        let end = self
+            .input
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(self.input.len());
+        let (word, rest) = self.input.split_at(end);
+        self.input = rest;
+
+        let word = word.to_ascii_lowercase();
+        match word.as_str() {
+            "" => Err(EvalError::Syntax("expected a value".to_string())),
+            "i" => Ok(Operand::I),
+            "pc" => Ok(Operand::Pc),
+            "sp" => Ok(Operand::Sp),
+            "dt" => Ok(Operand::Dt),
+            "st" => Ok(Operand::St),
+            _ if word.starts_with('v') => usize::from_str_radix(&word[1..], 16)
+                .map(Operand::Register)
+                .map_err(|_| EvalError::Syntax(format!("invalid register `{}`", word))),
+            _ => {
+                let value = match word.strip_prefix("0x") {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => word.parse(),
+                };
+                value
+                    .map(Operand::Literal)
+                    .map_err(|_| EvalError::Syntax(format!("invalid value `{}`", word)))
+            }
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Comparison, EvalError> {
+        const COMPARISONS: [(&str, Comparison); 6] = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+
+        self.input = self.input.trim_start();
+        for (symbol, comparison) in COMPARISONS {
+            if let Some(rest) = self.input.strip_prefix(symbol) {
+                self.input = rest;
+                return Ok(comparison);
+            }
+        }
+
+        Err(EvalError::Syntax("expected a comparison".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Chip8Interpreter;
+
+    fn evaluate(source: &str) -> Result<u32, EvalError> {
+        Expression::parse(source)?.evaluate(Chip8Interpreter::new().state())
+    }
+
+    fn is_syntax_error(source: &str) -> bool {
+        matches!(Expression::parse(source), Err(EvalError::Syntax(_)))
+    }
+
+    #[test]
+    fn valid_expressions_evaluate() {
+        assert_eq!(evaluate("pc"), Ok(0x200));
+        assert_eq!(evaluate("pc == 0x200"), Ok(1));
+        assert_eq!(evaluate("v3 > 2"), Ok(0));
+        assert_eq!(evaluate("[0] >= 0xf0"), Ok(1));
+    }
+
+    #[test]
+    fn unknown_registers_are_rejected() {
+        assert!(is_syntax_error("vg"));
+        assert!(is_syntax_error("v0 == vx1"));
+        assert_eq!(evaluate("v10"), Err(EvalError::InvalidRegister(16)));
+    }
+
+    #[test]
+    fn dangling_operators_are_rejected() {
+        assert!(is_syntax_error("v0 =="));
+        assert!(is_syntax_error("== 1"));
+        assert!(is_syntax_error("v0 = 1"));
+        assert!(is_syntax_error("v0 == 1 =="));
+    }
+
+    #[test]
+    fn unbalanced_brackets_are_rejected() {
+        assert!(is_syntax_error("[0x200"));
+        assert!(is_syntax_error("0x200]"));
+        assert!(is_syntax_error("[[i]"));
+        assert!(is_syntax_error("(v0)"));
+    }
+
+    #[test]
+    fn empty_input_is_rejected() {
+        assert!(is_syntax_error(""));
+        assert!(is_syntax_error("   "));
+        assert!(is_syntax_error("[]"));
+    }
+
+    #[test]
+    fn memory_outside_of_range_fails_to_evaluate() {
+        assert_eq!(
+            evaluate("[0x1000]"),
+            Err(EvalError::AddressOutOfRange(0x1000))
+        );
+    }
+}
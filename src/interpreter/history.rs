@@ -0,0 +1,88 @@
+use super::{Chip8InterpreterState, REGISTER_COUNT, RPL_FLAG_COUNT, SCREEN_WIDTH, STACK_SIZE};
+
+/// What a single tick changed, holding the values from before it so that it can be undone
+///
+/// Memory and screen are kept as lists of the bytes that changed rather than as copies, which
+/// keeps most deltas around 150 bytes where a full state takes over 6KB.
+pub(super) struct StateDelta {
+    registers: [u8; REGISTER_COUNT],
+    stack: [u16; STACK_SIZE],
+    input_keys: u32,
+    i: u16,
+    st: u8,
+    dt: u8,
+    pub(super) pc: u16,
+    sp: usize,
+    plane_mask: u8,
+    hires: bool,
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    /// Address and previous value of every memory byte the tick changed
+    memory: Vec<(u16, u8)>,
+    /// Index and previous value of every pixel the tick changed, counted row by row
+    screen: Vec<(u16, u8)>,
+    /// Byte `Random` took from the random number generator, which can't be undone, so it is
+    /// handed out again when the instruction runs again instead
+    pub(super) random: Option<u8>,
+}
+
+impl StateDelta {
+    /// Delta that takes `after` back to `before`
+    pub(super) fn between(before: &Chip8InterpreterState, after: &Chip8InterpreterState) -> Self {
+        let memory = changed_bytes(&before.memory, &after.memory);
+        let screen = changed_bytes(before.screen.as_flattened(), after.screen.as_flattened());
+
+        StateDelta {
+            registers: before.registers,
+            stack: before.stack,
+            input_keys: before.input_keys,
+            i: before.i,
+            st: before.st,
+            dt: before.dt,
+            pc: before.pc,
+            sp: before.sp,
+            plane_mask: before.plane_mask,
+            hires: before.hires,
+            rpl_flags: before.rpl_flags,
+            memory,
+            screen,
+            random: None,
+        }
+    }
+
+    /// Undo the tick on `state`, which has to be the state right after it
+    pub(super) fn revert(&self, state: &mut Chip8InterpreterState) {
+        state.registers = self.registers;
+        state.stack = self.stack;
+        state.input_keys = self.input_keys;
+        state.i = self.i;
+        state.st = self.st;
+        state.dt = self.dt;
+        state.pc = self.pc;
+        state.sp = self.sp;
+        state.plane_mask = self.plane_mask;
+        state.hires = self.hires;
+        state.rpl_flags = self.rpl_flags;
+        for &(address, value) in &self.memory {
+            state.memory[address as usize] = value;
+        }
+        for &(index, value) in &self.screen {
+            let index = index as usize;
+            state.screen[index / SCREEN_WIDTH][index % SCREEN_WIDTH] = value;
+        }
+    }
+
+    pub(super) fn changes_memory(&self) -> bool {
+        !self.memory.is_empty()
+    }
+}
+
+/// Index and value in `before` of every byte that differs in `after`
+fn changed_bytes(before: &[u8], after: &[u8]) -> Vec<(u16, u8)> {
+    before
+        .iter()
+        .zip(after)
+        .enumerate()
+        .filter(|(_, (before, after))| before != after)
+        .map(|(index, (&before, _))| (index as u16, before))
+        .collect()
+}
@@ -1,4 +1,6 @@
 use super::error::Chip8InterpreterError;
+use super::variant::InstructionSet;
+use serde::Serialize;
 use std::fmt::{Display, Formatter};
 
 #[derive(Clone, Copy, Debug)]
@@ -15,10 +17,29 @@ pub enum Chip8Instruction {
     Call { address: u16 },
     /// Return from subroutine
     Return,
-    /// Store register values in memory
+    /// Stop execution for good (SUPER-CHIP)
+    Exit,
+    /// Store register values V0..=VX in memory starting at I
+    ///
+    /// Decoded from `FX55` with `count = X + 1`, so `F055` stores only V0 and `FF55` stores all
+    /// sixteen registers.
     StoreRegisters { count: usize },
-    /// Load register values from memory
+    /// Load register values V0..=VX from memory starting at I
+    ///
+    /// Decoded from `FX65` with `count = X + 1`, mirroring `StoreRegisters`.
     LoadRegisters { count: usize },
+    /// Store register values V0..=VX in the RPL user flags (SUPER-CHIP)
+    ///
+    /// Decoded from `FX75` with `count = X + 1`, only the first `RPL_FLAG_COUNT` are stored.
+    StoreFlags { count: usize },
+    /// Load register values V0..=VX from the RPL user flags (SUPER-CHIP)
+    ///
+    /// Decoded from `FX85` with `count = X + 1`, only the first `RPL_FLAG_COUNT` are loaded.
+    LoadFlags { count: usize },
+    /// Store register values VX..=VY in memory starting at I, descending if X > Y (XO-CHIP)
+    StoreRegisterRange { x: usize, y: usize },
+    /// Load register values VX..=VY from memory starting at I, descending if X > Y (XO-CHIP)
+    LoadRegisterRange { x: usize, y: usize },
 
     ///  Jump to address
     Jump { address: u16 },
@@ -27,12 +48,24 @@ pub enum Chip8Instruction {
 
     /// Clear screen
     ClearScreen,
+    /// Scroll the screen down by amount high resolution pixels (SUPER-CHIP)
+    ScrollDown { amount: usize },
+    /// Scroll the screen right by 4 high resolution pixels (SUPER-CHIP)
+    ScrollRight,
+    /// Scroll the screen left by 4 high resolution pixels (SUPER-CHIP)
+    ScrollLeft,
+    /// Switch to 64x32 low resolution (SUPER-CHIP)
+    LowResolution,
+    /// Switch to 128x64 high resolution (SUPER-CHIP)
+    HighResolution,
     /// Select font character sprite to correspond with value stored in register
     SelectCharacter { register: usize },
     /// Store BCD representation of value from register
     StoreBcd { register: usize },
     /// Display sprite
     Draw { x: usize, y: usize, len: usize },
+    /// Select the display planes drawn to, one bit per plane (XO-CHIP)
+    SelectPlane { mask: u8 },
 
     /// Skip next instruction if value stored in register is equal to value
     SkipIfEqualValue { register: usize, value: u8 },
@@ -49,6 +82,11 @@ pub enum Chip8Instruction {
 
     /// Set register I to value
     SetIndex { address: u16 },
+    /// Set register I to the 16-bit address in the word following the opcode (XO-CHIP)
+    ///
+    /// Decoded from `F000 NNNN`. The opcode alone decodes with an address of 0, the interpreter
+    /// fills in the address when reading the instruction from memory.
+    LoadLongIndex { address: u16 },
     /// Add value from register to index address
     AddIndex { register: usize },
 
@@ -86,6 +124,150 @@ pub enum Chip8Instruction {
     ShiftLeft { x: usize, y: usize },
 }
 
+impl Chip8Instruction {
+    /// Instruction set that introduced this instruction
+    pub fn instruction_set(&self) -> InstructionSet {
+        match self {
+            Chip8Instruction::Exit
+            | Chip8Instruction::ScrollDown { .. }
+            | Chip8Instruction::ScrollRight
+            | Chip8Instruction::ScrollLeft
+            | Chip8Instruction::LowResolution
+            | Chip8Instruction::HighResolution
+            | Chip8Instruction::StoreFlags { .. }
+            | Chip8Instruction::LoadFlags { .. } => InstructionSet::SuperChip,
+            Chip8Instruction::StoreRegisterRange { .. }
+            | Chip8Instruction::LoadRegisterRange { .. }
+            | Chip8Instruction::LoadLongIndex { .. }
+            | Chip8Instruction::SelectPlane { .. } => InstructionSet::XoChip,
+            _ => InstructionSet::Chip8,
+        }
+    }
+
+    /// Encode the instruction as the opcode it decodes from
+    ///
+    /// `NoOperation` is decoded from every `0NNN` that isn't another instruction and encodes as
+    /// `0000`. `LoadLongIndex` encodes as `F000` alone, its address goes in the word after it.
+    /// Fields are cut to the bits the opcode has room for.
+    pub fn to_opcode(&self) -> u16 {
+        match *self {
+            Chip8Instruction::NoOperation => 0x0000,
+            Chip8Instruction::Syscall { address } => encode_nnn(0x0, address),
+            Chip8Instruction::Random { register, mask } => encode_xnn(0xc, register, mask),
+            Chip8Instruction::Call { address } => encode_nnn(0x2, address),
+            Chip8Instruction::Return => 0x00ee,
+            Chip8Instruction::Exit => 0x00fd,
+            Chip8Instruction::StoreRegisters { count } => {
+                encode_xnn(0xf, count.saturating_sub(1), 0x55)
+            }
+            Chip8Instruction::LoadRegisters { count } => {
+                encode_xnn(0xf, count.saturating_sub(1), 0x65)
+            }
+            Chip8Instruction::StoreFlags { count } => {
+                encode_xnn(0xf, count.saturating_sub(1), 0x75)
+            }
+            Chip8Instruction::LoadFlags { count } => encode_xnn(0xf, count.saturating_sub(1), 0x85),
+            Chip8Instruction::StoreRegisterRange { x, y } => encode_xyn(0x5, x, y, 0x2),
+            Chip8Instruction::LoadRegisterRange { x, y } => encode_xyn(0x5, x, y, 0x3),
+            Chip8Instruction::Jump { address } => encode_nnn(0x1, address),
+            Chip8Instruction::JumpRelative { address } => encode_nnn(0xb, address),
+            Chip8Instruction::ClearScreen => 0x00e0,
+            Chip8Instruction::ScrollDown { amount } => 0x00c0 | (amount as u16 & 0x0f),
+            Chip8Instruction::ScrollRight => 0x00fb,
+            Chip8Instruction::ScrollLeft => 0x00fc,
+            Chip8Instruction::LowResolution => 0x00fe,
+            Chip8Instruction::HighResolution => 0x00ff,
+            Chip8Instruction::SelectCharacter { register } => encode_xnn(0xf, register, 0x29),
+            Chip8Instruction::StoreBcd { register } => encode_xnn(0xf, register, 0x33),
+            Chip8Instruction::Draw { x, y, len } => encode_xyn(0xd, x, y, len as u16),
+            Chip8Instruction::SelectPlane { mask } => encode_xnn(0xf, mask as usize, 0x01),
+            Chip8Instruction::SkipIfEqualValue { register, value } => {
+                encode_xnn(0x3, register, value)
+            }
+            Chip8Instruction::SkipIfEqualRegister { x, y } => encode_xyn(0x5, x, y, 0x0),
+            Chip8Instruction::SkipIfNotEqualValue { register, value } => {
+                encode_xnn(0x4, register, value)
+            }
+            Chip8Instruction::SkipIfNotEqualRegister { x, y } => encode_xyn(0x9, x, y, 0x0),
+            Chip8Instruction::SkipIfKeyPressed { register } => encode_xnn(0xe, register, 0x9e),
+            Chip8Instruction::SkipIfKeyNotPressed { register } => encode_xnn(0xe, register, 0xa1),
+            Chip8Instruction::SetIndex { address } => encode_nnn(0xa, address),
+            Chip8Instruction::LoadLongIndex { .. } => 0xf000,
+            Chip8Instruction::AddIndex { register } => encode_xnn(0xf, register, 0x1e),
+            Chip8Instruction::LoadValue { register, value } => encode_xnn(0x6, register, value),
+            Chip8Instruction::Copy { x, y } => encode_xyn(0x8, x, y, 0x0),
+            Chip8Instruction::ReadDelayTimer { register } => encode_xnn(0xf, register, 0x07),
+            Chip8Instruction::SetDelayTimer { register } => encode_xnn(0xf, register, 0x15),
+            Chip8Instruction::SetSoundTimer { register } => encode_xnn(0xf, register, 0x18),
+            Chip8Instruction::WaitForKey { register } => encode_xnn(0xf, register, 0x0a),
+            Chip8Instruction::AddValue { register, value } => encode_xnn(0x7, register, value),
+            Chip8Instruction::AddRegister { x, y } => encode_xyn(0x8, x, y, 0x4),
+            Chip8Instruction::SubtractVxVy { x, y } => encode_xyn(0x8, x, y, 0x5),
+            Chip8Instruction::SubtractVyVx { x, y } => encode_xyn(0x8, x, y, 0x7),
+            Chip8Instruction::Or { x, y } => encode_xyn(0x8, x, y, 0x1),
+            Chip8Instruction::And { x, y } => encode_xyn(0x8, x, y, 0x2),
+            Chip8Instruction::Xor { x, y } => encode_xyn(0x8, x, y, 0x3),
+            Chip8Instruction::ShiftRight { x, y } => encode_xyn(0x8, x, y, 0x6),
+            Chip8Instruction::ShiftLeft { x, y } => encode_xyn(0x8, x, y, 0xe),
+        }
+    }
+}
+
+/// Opcode `hNNN`
+fn encode_nnn(high: u16, address: u16) -> u16 {
+    high << 12 | (address & 0x0fff)
+}
+
+/// Opcode `hXNN`
+fn encode_xnn(high: u16, x: usize, value: u8) -> u16 {
+    high << 12 | (x as u16 & 0x0f) << 8 | value as u16
+}
+
+/// Opcode `hXYN`
+fn encode_xyn(high: u16, x: usize, y: usize, low: u16) -> u16 {
+    high << 12 | (x as u16 & 0x0f) << 8 | (y as u16 & 0x0f) << 4 | (low & 0x0f)
+}
+
+/// Instruction decoded from memory, together with where it was found and its raw encoding
+#[derive(Debug)]
+pub struct DecodedInstruction {
+    /// Address of the first byte of the instruction
+    pub address: u16,
+    /// Raw opcode, also kept when it doesn't decode to a valid instruction
+    pub opcode: u16,
+    pub instruction: Result<Chip8Instruction, Chip8InterpreterError>,
+}
+
+/// Serializable summary of a `DecodedInstruction`, for consumption by external tools
+#[derive(Clone, Debug, Serialize)]
+pub struct DisassemblyEntry {
+    pub address: u16,
+    pub opcode: u16,
+    /// Assembly of the instruction, empty if it didn't decode
+    pub mnemonic: String,
+    /// Instruction set that introduced the instruction, `None` if it didn't decode
+    pub category: Option<InstructionSet>,
+    /// Whether the opcode decoded to a valid instruction
+    pub valid: bool,
+    /// Name the user gave the address, left out of the JSON if there is none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl From<&DecodedInstruction> for DisassemblyEntry {
+    fn from(decoded: &DecodedInstruction) -> Self {
+        let instruction = decoded.instruction.as_ref().ok();
+        DisassemblyEntry {
+            address: decoded.address,
+            opcode: decoded.opcode,
+            mnemonic: instruction.map(ToString::to_string).unwrap_or_default(),
+            category: instruction.map(Chip8Instruction::instruction_set),
+            valid: instruction.is_some(),
+            label: None,
+        }
+    }
+}
+
 impl Display for Chip8Instruction {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -104,12 +286,27 @@ impl Display for Chip8Instruction {
             Chip8Instruction::Return => {
                 write!(f, "{}", "Return")
             }
+            Chip8Instruction::Exit => {
+                write!(f, "{}", "Exit")
+            }
             Chip8Instruction::StoreRegisters { count } => {
                 write!(f, "StoreRegisters({})", count)
             }
             Chip8Instruction::LoadRegisters { count } => {
                 write!(f, "LoadRegisters({})", count)
             }
+            Chip8Instruction::StoreFlags { count } => {
+                write!(f, "StoreFlags({})", count)
+            }
+            Chip8Instruction::LoadFlags { count } => {
+                write!(f, "LoadFlags({})", count)
+            }
+            Chip8Instruction::StoreRegisterRange { x, y } => {
+                write!(f, "StoreRegisterRange(x: {}, y: {})", x, y)
+            }
+            Chip8Instruction::LoadRegisterRange { x, y } => {
+                write!(f, "LoadRegisterRange(x: {}, y: {})", x, y)
+            }
             Chip8Instruction::Jump { address } => {
                 write!(f, "Jump {:04x}", address)
             }
@@ -119,6 +316,21 @@ impl Display for Chip8Instruction {
             Chip8Instruction::ClearScreen => {
                 write!(f, "{}", "ClearScreen")
             }
+            Chip8Instruction::ScrollDown { amount } => {
+                write!(f, "ScrollDown({})", amount)
+            }
+            Chip8Instruction::ScrollRight => {
+                write!(f, "{}", "ScrollRight")
+            }
+            Chip8Instruction::ScrollLeft => {
+                write!(f, "{}", "ScrollLeft")
+            }
+            Chip8Instruction::LowResolution => {
+                write!(f, "{}", "LowResolution")
+            }
+            Chip8Instruction::HighResolution => {
+                write!(f, "{}", "HighResolution")
+            }
             Chip8Instruction::SelectCharacter { register } => {
                 write!(f, "SelectCharacter(V{:x})", register)
             }
@@ -128,6 +340,9 @@ impl Display for Chip8Instruction {
             Chip8Instruction::Draw { x, y, len } => {
                 write!(f, "Draw(x: {}, y: {}, length: {})", x, y, len)
             }
+            Chip8Instruction::SelectPlane { mask } => {
+                write!(f, "Plane({})", mask)
+            }
             Chip8Instruction::SkipIfEqualValue { register, value } => {
                 write!(f, "SkipNext if V{:x} == {}", register, value)
             }
@@ -149,6 +364,9 @@ impl Display for Chip8Instruction {
             Chip8Instruction::SetIndex { address } => {
                 write!(f, "I := {:04x}", address)
             }
+            Chip8Instruction::LoadLongIndex { address } => {
+                write!(f, "I := long {:04x}", address)
+            }
             Chip8Instruction::AddIndex { register } => {
                 write!(f, "I += V{:x}", register)
             }
@@ -209,6 +427,14 @@ impl TryFrom<u16> for Chip8Instruction {
             0x0 => match opcode {
                 0x00e0 => Ok(Chip8Instruction::ClearScreen),
                 0x00ee => Ok(Chip8Instruction::Return),
+                0x00c0..=0x00cf => Ok(Chip8Instruction::ScrollDown {
+                    amount: (opcode & 0x0f) as usize,
+                }),
+                0x00fb => Ok(Chip8Instruction::ScrollRight),
+                0x00fc => Ok(Chip8Instruction::ScrollLeft),
+                0x00fd => Ok(Chip8Instruction::Exit),
+                0x00fe => Ok(Chip8Instruction::LowResolution),
+                0x00ff => Ok(Chip8Instruction::HighResolution),
                 _ => Ok(Chip8Instruction::NoOperation),
                 // _ => Ok(Chip8Instruction::Syscall {
                 //     address: opcode & 0x0fff,
@@ -228,13 +454,21 @@ impl TryFrom<u16> for Chip8Instruction {
                 register: ((opcode >> 8) & 0x0f) as usize,
                 value: (opcode & 0xff) as u8,
             }),
-            0x5 => {
-                // TODO: invalid instruction if last nibble != 0?
-                Ok(Chip8Instruction::SkipIfEqualRegister {
+            0x5 => match opcode & 0x0f {
+                0x2 => Ok(Chip8Instruction::StoreRegisterRange {
                     x: ((opcode >> 8) & 0x0f) as usize,
                     y: ((opcode >> 4) & 0x0f) as usize,
-                })
-            }
+                }),
+                0x3 => Ok(Chip8Instruction::LoadRegisterRange {
+                    x: ((opcode >> 8) & 0x0f) as usize,
+                    y: ((opcode >> 4) & 0x0f) as usize,
+                }),
+                0x0 => Ok(Chip8Instruction::SkipIfEqualRegister {
+                    x: ((opcode >> 8) & 0x0f) as usize,
+                    y: ((opcode >> 4) & 0x0f) as usize,
+                }),
+                _ => Err(Chip8InterpreterError::InvalidInstruction(opcode)),
+            },
             0x6 => Ok(Chip8Instruction::LoadValue {
                 register: ((opcode >> 8) & 0x0f) as usize,
                 value: (opcode & 0xff) as u8,
@@ -243,6 +477,7 @@ impl TryFrom<u16> for Chip8Instruction {
                 register: ((opcode >> 8) & 0x0f) as usize,
                 value: (opcode & 0xff) as u8,
             }),
+            // Only 8XY0..=8XY7 and 8XYE are defined, every other low nibble is invalid
             0x8 => match opcode & 0x000f {
                 0x0 => Ok(Chip8Instruction::Copy {
                     x: ((opcode >> 8) & 0x0f) as usize,
@@ -282,13 +517,13 @@ impl TryFrom<u16> for Chip8Instruction {
                 }),
                 _ => Err(Chip8InterpreterError::InvalidInstruction(opcode)),
             },
-            0x9 => {
-                // TODO: invalid instruction if last nibble != 0?
-                Ok(Chip8Instruction::SkipIfNotEqualRegister {
+            0x9 => match opcode & 0x0f {
+                0x0 => Ok(Chip8Instruction::SkipIfNotEqualRegister {
                     x: ((opcode >> 8) & 0x0f) as usize,
                     y: ((opcode >> 4) & 0x0f) as usize,
-                })
-            }
+                }),
+                _ => Err(Chip8InterpreterError::InvalidInstruction(opcode)),
+            },
             0xa => Ok(Chip8Instruction::SetIndex {
                 address: opcode & 0x0fff,
             }),
@@ -320,6 +555,11 @@ impl TryFrom<u16> for Chip8Instruction {
             0xf => {
                 let register = ((opcode >> 8) & 0x0f) as usize;
                 match opcode & 0xff {
+                    0x00 if register == 0 => Ok(Chip8Instruction::LoadLongIndex { address: 0 }),
+                    // The register nibble holds the plane mask
+                    0x01 => Ok(Chip8Instruction::SelectPlane {
+                        mask: register as u8,
+                    }),
                     0x07 => Ok(Chip8Instruction::ReadDelayTimer { register }),
                     0x0a => Ok(Chip8Instruction::WaitForKey { register }),
                     0x15 => Ok(Chip8Instruction::SetDelayTimer { register }),
@@ -327,12 +567,19 @@ impl TryFrom<u16> for Chip8Instruction {
                     0x1e => Ok(Chip8Instruction::AddIndex { register }),
                     0x29 => Ok(Chip8Instruction::SelectCharacter { register }),
                     0x33 => Ok(Chip8Instruction::StoreBcd { register }),
+                    // The register range is inclusive, hence the count is one more than X
                     0x55 => Ok(Chip8Instruction::StoreRegisters {
                         count: register + 1,
                     }),
                     0x65 => Ok(Chip8Instruction::LoadRegisters {
                         count: register + 1,
                     }),
+                    0x75 => Ok(Chip8Instruction::StoreFlags {
+                        count: register + 1,
+                    }),
+                    0x85 => Ok(Chip8Instruction::LoadFlags {
+                        count: register + 1,
+                    }),
                     _ => Err(Chip8InterpreterError::InvalidInstruction(opcode)),
                 }
             }
@@ -340,3 +587,92 @@ impl TryFrom<u16> for Chip8Instruction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodable_opcodes_round_trip() {
+        for opcode in 0..=u16::MAX {
+            let instruction = match Chip8Instruction::try_from(opcode) {
+                Ok(instruction) => instruction,
+                Err(_) => continue,
+            };
+            let expected = match instruction {
+                Chip8Instruction::NoOperation => 0x0000,
+                _ => opcode,
+            };
+            assert_eq!(
+                instruction.to_opcode(),
+                expected,
+                "{:04x} decodes to {}",
+                opcode,
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    fn zero_count_encodes_without_underflow() {
+        assert_eq!(
+            Chip8Instruction::StoreRegisters { count: 0 }.to_opcode(),
+            0xf055
+        );
+        assert_eq!(Chip8Instruction::LoadFlags { count: 0 }.to_opcode(), 0xf085);
+    }
+
+    #[test]
+    fn register_skips_need_a_zero_low_nibble() {
+        assert!(matches!(
+            Chip8Instruction::try_from(0x5120),
+            Ok(Chip8Instruction::SkipIfEqualRegister { x: 1, y: 2 })
+        ));
+        assert!(matches!(
+            Chip8Instruction::try_from(0x9120),
+            Ok(Chip8Instruction::SkipIfNotEqualRegister { x: 1, y: 2 })
+        ));
+        assert!(matches!(
+            Chip8Instruction::try_from(0x5121),
+            Err(Chip8InterpreterError::InvalidInstruction(0x5121))
+        ));
+        assert!(matches!(
+            Chip8Instruction::try_from(0x9121),
+            Err(Chip8InterpreterError::InvalidInstruction(0x9121))
+        ));
+    }
+
+    #[test]
+    fn arithmetic_family_decodes_only_defined_sub_opcodes() {
+        let expected = [
+            Some("Copy { x: 1, y: 2 }"),
+            Some("Or { x: 1, y: 2 }"),
+            Some("And { x: 1, y: 2 }"),
+            Some("Xor { x: 1, y: 2 }"),
+            Some("AddRegister { x: 1, y: 2 }"),
+            Some("SubtractVxVy { x: 1, y: 2 }"),
+            Some("ShiftRight { x: 1, y: 2 }"),
+            Some("SubtractVyVx { x: 1, y: 2 }"),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some("ShiftLeft { x: 1, y: 2 }"),
+            None,
+        ];
+        for (nibble, expected) in expected.into_iter().enumerate() {
+            let opcode = 0x8120 | nibble as u16;
+            match (Chip8Instruction::try_from(opcode), expected) {
+                (Ok(instruction), Some(expected)) => {
+                    assert_eq!(format!("{:?}", instruction), expected)
+                }
+                (Err(Chip8InterpreterError::InvalidInstruction(invalid)), None) => {
+                    assert_eq!(invalid, opcode)
+                }
+                (result, _) => panic!("{:04x} decoded to {:?}", opcode, result),
+            }
+        }
+    }
+}
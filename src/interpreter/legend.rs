@@ -0,0 +1,131 @@
+use super::{Chip8Instruction, Chip8Variant, InstructionSet};
+
+/// Opcode pattern of every instruction along with an opcode matching it
+///
+/// X and Y stand for register numbers, N, NN and NNN for immediate values. Everything else in the
+/// legend is taken from decoding the example, so a new instruction only needs an entry here.
+const OPCODE_PATTERNS: [(&str, u16); 47] = [
+    ("0NNN", 0x0000),
+    ("00CN", 0x00c4),
+    ("00E0", 0x00e0),
+    ("00EE", 0x00ee),
+    ("00FB", 0x00fb),
+    ("00FC", 0x00fc),
+    ("00FD", 0x00fd),
+    ("00FE", 0x00fe),
+    ("00FF", 0x00ff),
+    ("1NNN", 0x1300),
+    ("2NNN", 0x2300),
+    ("3XNN", 0x3105),
+    ("4XNN", 0x4105),
+    ("5XY0", 0x5120),
+    ("5XY2", 0x5122),
+    ("5XY3", 0x5123),
+    ("6XNN", 0x6105),
+    ("7XNN", 0x7105),
+    ("8XY0", 0x8120),
+    ("8XY1", 0x8121),
+    ("8XY2", 0x8122),
+    ("8XY3", 0x8123),
+    ("8XY4", 0x8124),
+    ("8XY5", 0x8125),
+    ("8XY6", 0x8126),
+    ("8XY7", 0x8127),
+    ("8XYE", 0x812e),
+    ("9XY0", 0x9120),
+    ("ANNN", 0xa300),
+    ("BNNN", 0xb300),
+    ("CXNN", 0xc10f),
+    ("DXYN", 0xd125),
+    ("EX9E", 0xe19e),
+    ("EXA1", 0xe1a1),
+    ("F000 NNNN", 0xf000),
+    ("FN01", 0xf301),
+    ("FX07", 0xf107),
+    ("FX0A", 0xf10a),
+    ("FX15", 0xf115),
+    ("FX18", 0xf118),
+    ("FX1E", 0xf11e),
+    ("FX29", 0xf129),
+    ("FX33", 0xf133),
+    ("FX55", 0xf155),
+    ("FX65", 0xf165),
+    ("FX75", 0xf175),
+    ("FX85", 0xf185),
+];
+
+/// Entry of the opcode legend
+#[derive(Clone, Debug)]
+pub struct OpcodeInfo {
+    /// Opcode with its operands replaced by placeholders, such as `DXYN`
+    pub pattern: &'static str,
+    /// Example of the instruction as shown in the disassembly
+    pub example: Chip8Instruction,
+    pub category: InstructionSet,
+    /// Variants that can execute the instruction
+    pub variants: Vec<Chip8Variant>,
+}
+
+/// Every instruction the interpreter can decode, ordered by opcode
+pub fn opcode_legend() -> Vec<OpcodeInfo> {
+    OPCODE_PATTERNS
+        .iter()
+        .filter_map(|&(pattern, opcode)| {
+            let example = Chip8Instruction::try_from(opcode).ok()?;
+            let category = example.instruction_set();
+            Some(OpcodeInfo {
+                pattern,
+                example,
+                category,
+                variants: Chip8Variant::ALL
+                    .into_iter()
+                    .filter(|variant| variant.supports(category))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::discriminant;
+
+    #[test]
+    fn every_example_decodes() {
+        for (pattern, opcode) in OPCODE_PATTERNS {
+            assert!(
+                Chip8Instruction::try_from(opcode).is_ok(),
+                "{} example {:04x} doesn't decode",
+                pattern,
+                opcode
+            );
+        }
+        assert_eq!(opcode_legend().len(), OPCODE_PATTERNS.len());
+    }
+
+    #[test]
+    fn every_instruction_has_an_entry() {
+        let legend = opcode_legend();
+        for opcode in 0..=u16::MAX {
+            if let Ok(instruction) = Chip8Instruction::try_from(opcode) {
+                assert!(
+                    legend
+                        .iter()
+                        .any(|info| discriminant(&info.example) == discriminant(&instruction)),
+                    "{:04x} decodes to {:?}, which has no legend entry",
+                    opcode,
+                    instruction
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_variant_has_entries() {
+        let legend = opcode_legend();
+        for variant in Chip8Variant::ALL {
+            assert!(legend.iter().any(|info| info.variants.contains(&variant)));
+        }
+    }
+}
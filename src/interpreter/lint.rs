@@ -0,0 +1,449 @@
+use super::{Chip8Instruction, DecodedInstruction, REGISTER_COUNT, RPL_FLAG_COUNT};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// Decoded program as produced by `Chip8Interpreter::instructions`
+pub type Program = [DecodedInstruction];
+
+#[derive(Clone, Debug)]
+pub struct LintFinding {
+    /// Address of the offending instruction
+    pub address: u16,
+    /// Human readable description of the problem
+    pub message: String,
+}
+
+impl Display for LintFinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04x}: {}", self.address, self.message)
+    }
+}
+
+pub trait LintRule {
+    fn check(&self, program: &Program) -> Vec<LintFinding>;
+}
+
+/// Timer set from a register that has not been written by any earlier instruction
+pub struct UninitializedTimerSource;
+
+/// Sprite drawn before any instruction has pointed I somewhere
+pub struct DrawWithoutIndex;
+
+/// Jump or call whose target is not the start of a valid instruction in the program
+pub struct JumpIntoData;
+
+/// Instructions that can not be reached from the entry point
+pub struct UnreachableCode;
+
+/// VF read after data stored in it was overwritten by an instruction using it as the flag register
+///
+/// This is advisory, programs that only ever use VF for flags are not affected.
+pub struct FlagClobbersData;
+
+pub fn default_rules() -> Vec<Box<dyn LintRule>> {
+    vec![
+        Box::new(UninitializedTimerSource),
+        Box::new(DrawWithoutIndex),
+        Box::new(JumpIntoData),
+        Box::new(UnreachableCode),
+        Box::new(FlagClobbersData),
+    ]
+}
+
+/// Run every rule over the program, returning the findings ordered by address
+pub fn lint(program: &Program, rules: &[Box<dyn LintRule>]) -> Vec<LintFinding> {
+    let mut findings: Vec<LintFinding> =
+        rules.iter().flat_map(|rule| rule.check(program)).collect();
+    findings.sort_by_key(|finding| finding.address);
+    findings
+}
+
+fn written_registers(instruction: &Chip8Instruction) -> Vec<usize> {
+    match *instruction {
+        Chip8Instruction::Random { register, .. }
+        | Chip8Instruction::LoadValue { register, .. }
+        | Chip8Instruction::AddValue { register, .. }
+        | Chip8Instruction::ReadDelayTimer { register }
+        | Chip8Instruction::WaitForKey { register } => vec![register],
+        Chip8Instruction::LoadRegisters { count } => (0..count).collect(),
+        Chip8Instruction::LoadFlags { count } => (0..count.min(RPL_FLAG_COUNT)).collect(),
+        Chip8Instruction::LoadRegisterRange { x, y } => (x.min(y)..=x.max(y)).collect(),
+        Chip8Instruction::Copy { x, .. }
+        | Chip8Instruction::Or { x, .. }
+        | Chip8Instruction::And { x, .. }
+        | Chip8Instruction::Xor { x, .. } => vec![x],
+        Chip8Instruction::AddRegister { x, .. }
+        | Chip8Instruction::SubtractVxVy { x, .. }
+        | Chip8Instruction::SubtractVyVx { x, .. }
+        | Chip8Instruction::ShiftRight { x, .. }
+        | Chip8Instruction::ShiftLeft { x, .. } => vec![x, 15],
+        Chip8Instruction::Draw { .. } => vec![15],
+        _ => vec![],
+    }
+}
+
+fn read_registers(instruction: &Chip8Instruction) -> Vec<usize> {
+    match *instruction {
+        Chip8Instruction::SelectCharacter { register }
+        | Chip8Instruction::StoreBcd { register }
+        | Chip8Instruction::SkipIfEqualValue { register, .. }
+        | Chip8Instruction::SkipIfNotEqualValue { register, .. }
+        | Chip8Instruction::SkipIfKeyPressed { register }
+        | Chip8Instruction::SkipIfKeyNotPressed { register }
+        | Chip8Instruction::AddIndex { register }
+        | Chip8Instruction::SetDelayTimer { register }
+        | Chip8Instruction::SetSoundTimer { register }
+        | Chip8Instruction::AddValue { register, .. } => vec![register],
+        Chip8Instruction::StoreRegisters { count } => (0..count).collect(),
+        Chip8Instruction::StoreFlags { count } => (0..count.min(RPL_FLAG_COUNT)).collect(),
+        Chip8Instruction::StoreRegisterRange { x, y } => (x.min(y)..=x.max(y)).collect(),
+        Chip8Instruction::JumpRelative { .. } => vec![0],
+        Chip8Instruction::Copy { y, .. } => vec![y],
+        Chip8Instruction::ShiftRight { x, .. } | Chip8Instruction::ShiftLeft { x, .. } => vec![x],
+        Chip8Instruction::Draw { x, y, .. }
+        | Chip8Instruction::SkipIfEqualRegister { x, y }
+        | Chip8Instruction::SkipIfNotEqualRegister { x, y }
+        | Chip8Instruction::Or { x, y }
+        | Chip8Instruction::And { x, y }
+        | Chip8Instruction::Xor { x, y }
+        | Chip8Instruction::AddRegister { x, y }
+        | Chip8Instruction::SubtractVxVy { x, y }
+        | Chip8Instruction::SubtractVyVx { x, y } => vec![x, y],
+        _ => vec![],
+    }
+}
+
+/// Whether the instruction always leaves a flag in VF, whatever else it writes
+fn sets_flag(instruction: &Chip8Instruction) -> bool {
+    matches!(
+        instruction,
+        Chip8Instruction::AddRegister { .. }
+            | Chip8Instruction::SubtractVxVy { .. }
+            | Chip8Instruction::SubtractVyVx { .. }
+            | Chip8Instruction::ShiftRight { .. }
+            | Chip8Instruction::ShiftLeft { .. }
+            | Chip8Instruction::Draw { .. }
+    )
+}
+
+impl LintRule for UninitializedTimerSource {
+    fn check(&self, program: &Program) -> Vec<LintFinding> {
+        let mut initialized = [false; REGISTER_COUNT];
+        let mut findings = vec![];
+
+        for DecodedInstruction {
+            address,
+            instruction,
+            ..
+        } in program
+        {
+            let instruction = match instruction {
+                Ok(instruction) => instruction,
+                Err(_) => continue,
+            };
+
+            match *instruction {
+                Chip8Instruction::SetDelayTimer { register }
+                | Chip8Instruction::SetSoundTimer { register }
+                    if !initialized[register] =>
+                {
+                    findings.push(LintFinding {
+                        address: *address,
+                        message: format!("timer set from uninitialized register V{:x}", register),
+                    });
+                }
+                _ => {}
+            }
+
+            for register in written_registers(instruction) {
+                initialized[register] = true;
+            }
+        }
+
+        findings
+    }
+}
+
+impl LintRule for DrawWithoutIndex {
+    fn check(&self, program: &Program) -> Vec<LintFinding> {
+        let mut index_set = false;
+        let mut findings = vec![];
+
+        for DecodedInstruction {
+            address,
+            instruction,
+            ..
+        } in program
+        {
+            match instruction {
+                Ok(Chip8Instruction::SetIndex { .. })
+                | Ok(Chip8Instruction::AddIndex { .. })
+                | Ok(Chip8Instruction::SelectCharacter { .. }) => index_set = true,
+                Ok(Chip8Instruction::Draw { .. }) if !index_set => {
+                    findings.push(LintFinding {
+                        address: *address,
+                        message: "sprite drawn before I was set".to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        findings
+    }
+}
+
+impl LintRule for JumpIntoData {
+    fn check(&self, program: &Program) -> Vec<LintFinding> {
+        let valid_targets: HashSet<u16> = program
+            .iter()
+            .filter(|decoded| decoded.instruction.is_ok())
+            .map(|decoded| decoded.address)
+            .collect();
+        let mut findings = vec![];
+
+        for DecodedInstruction {
+            address,
+            instruction,
+            ..
+        } in program
+        {
+            let target = match instruction {
+                Ok(Chip8Instruction::Jump { address }) | Ok(Chip8Instruction::Call { address }) => {
+                    *address
+                }
+                _ => continue,
+            };
+
+            if !valid_targets.contains(&target) {
+                findings.push(LintFinding {
+                    address: *address,
+                    message: format!("jump target {:04x} is not a valid instruction", target),
+                });
+            }
+        }
+
+        findings
+    }
+}
+
+/// Addresses of every instruction reachable from the first instruction of `program`
+///
+/// Returns `None` if the program contains computed jumps, as their targets can't be known
+/// statically.
+pub fn reachable_addresses(program: &Program) -> Option<HashSet<u16>> {
+    if program.iter().any(|decoded| {
+        matches!(
+            decoded.instruction,
+            Ok(Chip8Instruction::JumpRelative { .. })
+        )
+    }) {
+        return None;
+    }
+
+    let lookup: HashMap<u16, Chip8Instruction> = program
+        .iter()
+        .filter_map(|decoded| match decoded.instruction {
+            Ok(instruction) => Some((decoded.address, instruction)),
+            Err(_) => None,
+        })
+        .collect();
+
+    let mut reachable = HashSet::new();
+    let mut pending: Vec<_> = program
+        .first()
+        .map(|decoded| decoded.address)
+        .into_iter()
+        .collect();
+    while let Some(address) = pending.pop() {
+        let instruction = match lookup.get(&address) {
+            Some(instruction) => *instruction,
+            None => continue,
+        };
+        if !reachable.insert(address) {
+            continue;
+        }
+
+        match instruction {
+            Chip8Instruction::Jump { address } => pending.push(address),
+            Chip8Instruction::Return => {}
+            Chip8Instruction::LoadLongIndex { .. } => pending.push(address + 4),
+            Chip8Instruction::Call { address: target } => {
+                pending.push(target);
+                pending.push(address + 2);
+            }
+            Chip8Instruction::SkipIfEqualValue { .. }
+            | Chip8Instruction::SkipIfEqualRegister { .. }
+            | Chip8Instruction::SkipIfNotEqualValue { .. }
+            | Chip8Instruction::SkipIfNotEqualRegister { .. }
+            | Chip8Instruction::SkipIfKeyPressed { .. }
+            | Chip8Instruction::SkipIfKeyNotPressed { .. } => {
+                pending.push(address + 2);
+                pending.push(address + 4);
+            }
+            _ => pending.push(address + 2),
+        }
+    }
+
+    Some(reachable)
+}
+
+impl LintRule for UnreachableCode {
+    fn check(&self, program: &Program) -> Vec<LintFinding> {
+        // Computed jumps can go anywhere, so reachability can't be determined
+        let reachable = match reachable_addresses(program) {
+            Some(reachable) => reachable,
+            None => return vec![],
+        };
+
+        // Report only the start of each unreachable run to keep the output readable
+        let mut findings = vec![];
+        let mut in_unreachable_run = false;
+        for DecodedInstruction {
+            address,
+            instruction,
+            ..
+        } in program
+        {
+            if instruction.is_err() {
+                continue;
+            }
+
+            if reachable.contains(address) {
+                in_unreachable_run = false;
+            } else if !in_unreachable_run {
+                in_unreachable_run = true;
+                findings.push(LintFinding {
+                    address: *address,
+                    message: "unreachable code or data".to_string(),
+                });
+            }
+        }
+
+        findings
+    }
+}
+
+impl LintRule for FlagClobbersData {
+    fn check(&self, program: &Program) -> Vec<LintFinding> {
+        // Address of the last instruction that stored data in VF, and of the flag overwriting it
+        let mut data_written_at = None;
+        let mut clobbered_at = None;
+        let mut findings = vec![];
+
+        for DecodedInstruction {
+            address,
+            instruction,
+            ..
+        } in program
+        {
+            let instruction = match instruction {
+                Ok(instruction) => instruction,
+                Err(_) => continue,
+            };
+
+            if let (Some(written), Some(clobbered)) = (data_written_at, clobbered_at) {
+                if read_registers(instruction).contains(&15) {
+                    findings.push(LintFinding {
+                        address: *address,
+                        message: format!(
+                            "VF read as data stored at {:04x}, but the flag set at {:04x} overwrote it",
+                            written, clobbered
+                        ),
+                    });
+                    clobbered_at = None;
+                }
+            }
+
+            if sets_flag(instruction) {
+                if data_written_at.is_some() && clobbered_at.is_none() {
+                    clobbered_at = Some(*address);
+                }
+            } else if written_registers(instruction).contains(&15) {
+                data_written_at = Some(*address);
+                clobbered_at = None;
+            }
+        }
+
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Chip8Interpreter;
+
+    /// Addresses `rule` reports for `rom`
+    fn findings(rule: &dyn LintRule, rom: &[u8]) -> Vec<u16> {
+        let mut interp = Chip8Interpreter::new();
+        interp.try_load_rom(rom).unwrap();
+        let program: Vec<_> = interp.instructions().collect();
+        rule.check(&program)
+            .iter()
+            .map(|finding| finding.address)
+            .collect()
+    }
+
+    #[test]
+    fn timer_set_from_unwritten_register() {
+        assert_eq!(
+            findings(&UninitializedTimerSource, &[0xf3, 0x15, 0x12, 0x02]),
+            [0x200]
+        );
+        assert!(findings(&UninitializedTimerSource, &[0x63, 0x01, 0xf3, 0x18]).is_empty());
+    }
+
+    #[test]
+    fn draw_before_index_is_set() {
+        assert_eq!(
+            findings(&DrawWithoutIndex, &[0xd0, 0x15, 0x12, 0x02]),
+            [0x200]
+        );
+        assert!(findings(&DrawWithoutIndex, &[0xf0, 0x29, 0xd0, 0x15]).is_empty());
+    }
+
+    #[test]
+    fn jump_to_odd_address() {
+        assert_eq!(findings(&JumpIntoData, &[0x60, 0x01, 0x12, 0x03]), [0x202]);
+        assert_eq!(findings(&JumpIntoData, &[0x23, 0x00]), [0x200]);
+        assert!(findings(&JumpIntoData, &[0x60, 0x01, 0x12, 0x00]).is_empty());
+    }
+
+    #[test]
+    fn code_after_unconditional_jump() {
+        // Only the start of the unreachable run is reported
+        let rom = [0x12, 0x06, 0x60, 0x01, 0x61, 0x02, 0x12, 0x06];
+        assert_eq!(findings(&UnreachableCode, &rom), [0x202]);
+        // Both outcomes of a skip are reachable
+        let rom = [0x30, 0x01, 0x12, 0x06, 0x60, 0x01, 0x12, 0x06];
+        assert!(findings(&UnreachableCode, &rom).is_empty());
+    }
+
+    #[test]
+    fn clean_program_has_no_findings() {
+        // Draw the font sprite for V0, start the delay timer from V0 and spin
+        let rom = [0x60, 0x05, 0xa0, 0x00, 0xd0, 0x05, 0xf0, 0x15, 0x12, 0x08];
+        let mut interp = Chip8Interpreter::new();
+        interp.try_load_rom(&rom).unwrap();
+        assert!(interp.lint().is_empty());
+    }
+
+    #[test]
+    fn data_in_vf_clobbered_by_a_flag() {
+        // VF := 5, V0 += V1 overwrites it with the carry, then VF is read back as the data
+        let rom = [0x6f, 0x05, 0x80, 0x14, 0xff, 0x15, 0x12, 0x06];
+        assert_eq!(findings(&FlagClobbersData, &rom), [0x204]);
+    }
+
+    #[test]
+    fn vf_read_before_the_flag_or_only_used_for_flags() {
+        let rom = [0x6f, 0x05, 0xff, 0x15, 0x80, 0x14, 0x12, 0x06];
+        assert!(findings(&FlagClobbersData, &rom).is_empty());
+        let rom = [0x80, 0x14, 0xff, 0x15, 0x12, 0x04];
+        assert!(findings(&FlagClobbersData, &rom).is_empty());
+        // Storing data again after the flag makes the read fine
+        let rom = [0x6f, 0x05, 0x80, 0x14, 0x6f, 0x06, 0xff, 0x15];
+        assert!(findings(&FlagClobbersData, &rom).is_empty());
+    }
+}
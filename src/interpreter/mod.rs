@@ -1,37 +1,80 @@
+mod config;
+pub mod console;
+mod cost;
+mod crash;
+mod decode_cache;
+mod divergence;
 mod error;
+mod events;
+pub mod expr;
 mod font;
+mod history;
 mod instructions;
+mod legend;
+pub mod lint;
+mod rng;
+mod save;
+mod serde_arrays;
+mod style;
+pub mod trace;
+mod variant;
 
 use font::FONT_ROM;
+use history::StateDelta;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::default::Default;
-use std::fmt::format;
+use std::io::Write;
+use std::time::{Duration, Instant};
 
+pub use config::{Chip8Config, QuirkInfo, Quirks};
+pub use cost::CycleCosts;
+pub use crash::with_crash_report;
+pub use decode_cache::DecodeCache;
+pub use divergence::{trace_divergence, Divergence};
 pub use error::Chip8InterpreterError;
-pub use instructions::Chip8Instruction;
+pub use events::Chip8Event;
+pub use instructions::{Chip8Instruction, DecodedInstruction, DisassemblyEntry};
+pub use legend::{opcode_legend, OpcodeInfo};
+pub use lint::LintFinding;
+pub use rng::Rng;
+pub use save::{Chip8SaveBundle, Chip8SaveState};
+pub use style::DisassemblyStyle;
+pub use variant::{Chip8Variant, InstructionSet};
 
 pub const BASE_ADDRESS: u16 = 0x200;
 pub const MEMORY_SIZE: u16 = 4096;
 pub const STACK_SIZE: usize = 32;
 pub const REGISTER_COUNT: usize = 16;
+/// Number of HP-48 RPL user flags SUPER-CHIP can store registers in
+pub const RPL_FLAG_COUNT: usize = 8;
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+/// Number of XO-CHIP display planes, every pixel of the screen holds one bit per plane
+pub const PLANE_COUNT: usize = 2;
+/// Plane mask selecting every display plane
+pub const ALL_PLANES: u8 = (1 << PLANE_COUNT) - 1;
 
-const MAX_ROM_SIZE: u16 = MEMORY_SIZE - BASE_ADDRESS;
+/// Rate at which the delay and sound timers count down
+pub const TIMER_FREQUENCY: u32 = 60;
 
-const TICKS_PER_SECOND: usize = 500;
-const TIMER_FREQUENCY: usize = 60;
-const TIMER_TICK_INTERVAL: usize = TICKS_PER_SECOND / TIMER_FREQUENCY;
+/// Number of ticks between wall clock checks in `run_headless`
+const WATCHDOG_CHECK_INTERVAL: u64 = 1024;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Chip8InterpreterState {
     /// Registers
     pub registers: [u8; REGISTER_COUNT],
     /// Call stack
     pub stack: [u16; STACK_SIZE],
     /// Program memory
+    #[serde(with = "serde_arrays")]
     pub memory: [u8; MEMORY_SIZE as usize],
     /// Currently displayed screen data
+    ///
+    /// Bit 0 of each pixel is lit on the first display plane, bit 1 on the second XO-CHIP plane.
+    #[serde(with = "serde_arrays::nested")]
     pub screen: [[u8; SCREEN_WIDTH]; SCREEN_HEIGHT],
     /// Currently held input keys
     pub input_keys: u32,
@@ -45,6 +88,15 @@ pub struct Chip8InterpreterState {
     pub pc: u16,
     /// Stack pointer
     pub sp: usize,
+    /// Display planes that draws, clears and scrolls apply to
+    #[serde(default = "default_plane_mask")]
+    pub plane_mask: u8,
+    /// Whether the program switched to high resolution, the screen is still drawn at 64x32
+    #[serde(default)]
+    pub hires: bool,
+    /// HP-48 RPL user flags, written by `StoreFlags` and kept across resets like on the calculator
+    #[serde(default)]
+    pub rpl_flags: [u8; RPL_FLAG_COUNT],
 }
 
 impl Default for Chip8InterpreterState {
@@ -60,6 +112,9 @@ impl Default for Chip8InterpreterState {
             dt: 0,
             pc: BASE_ADDRESS,
             sp: 0,
+            plane_mask: default_plane_mask(),
+            hires: false,
+            rpl_flags: [0; RPL_FLAG_COUNT],
         };
 
         let font_mem = &mut state.memory[..FONT_ROM.len()];
@@ -69,34 +124,430 @@ impl Default for Chip8InterpreterState {
     }
 }
 
+fn default_plane_mask() -> u8 {
+    1
+}
+
+/// What `run_headless` does when the program waits for a key and no scheduled input is pending
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadlessKeyPolicy {
+    /// Keep stalling until the cycle limit or timeout is reached
+    Stall,
+    /// Press and release the given key
+    Press(u8),
+    /// Stop with `WaitingForKey`
+    Stop,
+}
+
 pub struct Chip8Interpreter {
     state: Chip8InterpreterState,
+    config: Chip8Config,
+    rng: Box<dyn Rng>,
     /// Keeps track of when to tick st and dt relative to master clock
     timer_counter: usize,
+    /// Count the timers down from `tick` rather than leaving it to the caller
+    tick_drives_timers: bool,
+    /// Contents of the currently loaded ROM as it was loaded
+    rom: Vec<u8>,
+    /// Number of ticks since the last reset
+    ticks: u64,
+    /// Cycles the instructions executed since the last reset cost, see `CycleCosts`
+    cycles: u64,
+    /// Input key masks to apply once the tick counter reaches the keyed tick
+    scheduled_input: BTreeMap<u64, u32>,
+    /// Keys released since the last executed instruction, for `wait_for_key_release`
+    released_keys: u32,
+    /// Set when the instruction executed by the last tick was a colliding draw
+    draw_collided: bool,
+    /// Instruction executed by the last tick, if any
+    last_instruction: Option<Chip8Instruction>,
+    /// Events emitted since they were last drained with `take_events`
+    events: Vec<Chip8Event>,
+    /// Set whenever the screen contents change, cleared by the caller
+    screen_dirty: bool,
+    /// Keep a copy of the screen whenever it is cleared, for debugging
+    capture_cleared_screen: bool,
+    /// Hold the delay and sound timers at their current values, for debugging
+    timers_frozen: bool,
+    /// Screen contents right before the most recent clear
+    last_cleared_screen: Option<[[u8; SCREEN_WIDTH]; SCREEN_HEIGHT]>,
+    /// Changes whenever memory or the way it is decoded changes, see `DecodeCache`
+    memory_generation: u64,
+    /// Changes made by the most recent ticks, newest last
+    history: VecDeque<StateDelta>,
+    /// Bytes drawn by undone `Random` instructions, handed out again before new ones, newest last
+    random_replay: Vec<u8>,
+    /// Byte drawn by the instruction being executed, recorded in its history entry
+    random_drawn: Option<u8>,
+    /// Most states kept in `history`
+    history_depth: usize,
+    /// Addresses to stop at before executing the instruction there
+    breakpoints: HashSet<u16>,
+    /// Breakpoint that was hit last, so that the next tick executes its instruction
+    resume_from_breakpoint: Option<u16>,
+    /// Addresses to stop at after an instruction writes to them
+    watchpoints: HashSet<u16>,
+    /// First watched write of the instruction being executed
+    watchpoint_hit: Option<(u16, u8)>,
+    /// Set once the program has executed `Exit`, ticks do nothing after that
+    halted: bool,
+    /// Whether the timers counted down since the last draw, see `Quirks::display_wait`
+    vblank: bool,
 }
 
 impl Chip8Interpreter {
     pub fn new() -> Self {
         let mut interp = Chip8Interpreter {
             state: Default::default(),
+            config: Default::default(),
+            rng: rng::entropy_rng(),
             timer_counter: 0,
+            tick_drives_timers: true,
+            rom: Vec::new(),
+            ticks: 0,
+            cycles: 0,
+            scheduled_input: BTreeMap::new(),
+            released_keys: 0,
+            draw_collided: false,
+            last_instruction: None,
+            events: Vec::new(),
+            screen_dirty: true,
+            capture_cleared_screen: false,
+            timers_frozen: false,
+            last_cleared_screen: None,
+            memory_generation: 0,
+            history: VecDeque::new(),
+            history_depth: 0,
+            random_replay: Vec::new(),
+            random_drawn: None,
+            breakpoints: HashSet::new(),
+            resume_from_breakpoint: None,
+            watchpoints: HashSet::new(),
+            watchpoint_hit: None,
+            halted: false,
+            vblank: true,
         };
 
         interp.reset();
         interp
     }
 
+    /// Interpreter whose random number generator is seeded with `seed`, so `Random` produces the
+    /// same bytes on every run
+    ///
+    /// The seed is also stored in the configuration, for `Chip8Config::reseed_on_reset`.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut interp = Self::new();
+        interp.config.seed = seed;
+        interp.rng = rng::seeded_rng(seed);
+        interp
+    }
+
+    /// Replace the random number generator `Random` takes its bytes from
+    ///
+    /// Resetting with `reseed_on_reset` set replaces it with one seeded from the configuration.
+    pub fn set_rng(&mut self, rng: impl Rng + 'static) {
+        self.rng = Box::new(rng);
+        // Bytes of the old generator no longer belong to the sequence
+        self.random_replay.clear();
+    }
+
+    /// Whether the buzzer should currently be sounding.
+    ///
+    /// The tone plays for as long as the sound timer is non-zero, so this is the single source of
+    /// truth for both the UI indicator and any audio output.
+    pub fn should_buzz(&self) -> bool {
+        self.state.st > 0
+    }
+
     pub fn is_sound_playing(&self) -> bool {
-        self.state.st > 1
+        self.should_buzz()
     }
 
     pub fn state(&self) -> &Chip8InterpreterState {
         &self.state
     }
 
+    pub fn config(&self) -> &Chip8Config {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: Chip8Config) {
+        // The variant decides which opcodes decode
+        if config.variant != self.config.variant {
+            self.memory_changed();
+        }
+        self.config = config;
+    }
+
+    /// Instructions executed per second, see `Chip8Config::clock_hz`
+    pub fn clock_rate(&self) -> u32 {
+        self.config.clock_hz()
+    }
+
+    /// Override the clock speed of the selected variant
+    pub fn set_clock_rate(&mut self, hz: u32) {
+        self.config.clock_hz = Some(hz);
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.config.quirks
+    }
+
+    /// Change the instruction behaviors that differ between interpreters, effective immediately
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.config.quirks = quirks;
+    }
+
     pub fn reset(&mut self) {
+        let rpl_flags = self.state.rpl_flags;
         self.state = Default::default();
+        self.state.rpl_flags = rpl_flags;
+        self.state.pc = self.config.initial_pc();
+        self.memory_changed();
+        if self.config.reseed_on_reset {
+            self.rng = rng::seeded_rng(self.config.seed);
+        }
         self.timer_counter = 0;
+        self.vblank = true;
+        self.rom.clear();
+        self.ticks = 0;
+        self.cycles = 0;
+        self.scheduled_input.clear();
+        self.history.clear();
+        self.random_replay.clear();
+        self.resume_from_breakpoint = None;
+        self.halted = false;
+        self.released_keys = 0;
+        self.draw_collided = false;
+        self.last_instruction = None;
+        self.events.clear();
+        self.screen_dirty = true;
+        self.last_cleared_screen = None;
+        if self.config.reset_clears_debug_state {
+            self.clear_debug_state();
+        }
+    }
+
+    /// Turn off the debugging aids held by the interpreter
+    pub fn clear_debug_state(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+        self.timers_frozen = false;
+        self.last_cleared_screen = None;
+    }
+
+    /// Capture the screen contents before every `ClearScreen` for later inspection
+    ///
+    /// This doesn't change how `ClearScreen` behaves, it only takes a copy of the screen.
+    pub fn set_capture_cleared_screen(&mut self, enabled: bool) {
+        self.capture_cleared_screen = enabled;
+    }
+
+    /// Stop the delay and sound timers from counting down while instructions keep executing
+    pub fn freeze_timers(&mut self, frozen: bool) {
+        self.timers_frozen = frozen;
+    }
+
+    pub fn timers_frozen(&self) -> bool {
+        self.timers_frozen
+    }
+
+    /// Stop with `BreakpointHit` whenever the instruction at `address` is about to be executed
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(address);
+    }
+
+    /// Returns whether there was a breakpoint at `address`
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn breakpoints(&self) -> &HashSet<u16> {
+        &self.breakpoints
+    }
+
+    /// Stop with `WatchpointHit` after an instruction writes to `address`
+    ///
+    /// The instruction completes before stopping, so PC already points past it.
+    pub fn add_watchpoint(&mut self, address: u16) {
+        self.watchpoints.insert(address);
+    }
+
+    /// Returns whether there was a watchpoint at `address`
+    pub fn remove_watchpoint(&mut self, address: u16) -> bool {
+        self.watchpoints.remove(&address)
+    }
+
+    pub fn watchpoints(&self) -> &HashSet<u16> {
+        &self.watchpoints
+    }
+
+    /// Choose whether `tick` counts the timers down at the rate implied by the clock speed
+    ///
+    /// This is the default. Callers with a real 60Hz schedule should turn it off and call
+    /// `tick_timers` themselves, so that the timers keep time however instructions are executed.
+    pub fn set_tick_drives_timers(&mut self, enabled: bool) {
+        self.tick_drives_timers = enabled;
+    }
+
+    pub fn tick_drives_timers(&self) -> bool {
+        self.tick_drives_timers
+    }
+
+    /// Screen contents right before the most recent `ClearScreen`, if capturing is enabled
+    pub fn last_cleared_screen(&self) -> Option<&[[u8; SCREEN_WIDTH]; SCREEN_HEIGHT]> {
+        self.last_cleared_screen.as_ref()
+    }
+
+    /// Whether the screen changed since the last call to `clear_screen_dirty`
+    pub fn screen_dirty(&self) -> bool {
+        self.screen_dirty
+    }
+
+    pub fn clear_screen_dirty(&mut self) {
+        self.screen_dirty = false;
+    }
+
+    pub fn rpl_flags(&self) -> &[u8; RPL_FLAG_COUNT] {
+        &self.state.rpl_flags
+    }
+
+    /// Replace the RPL flags, such as with ones persisted by an earlier session
+    pub fn set_rpl_flags(&mut self, flags: [u8; RPL_FLAG_COUNT]) {
+        self.state.rpl_flags = flags;
+    }
+
+    /// Whether the program has stopped itself with `Exit`
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Whether the program is stalled on `WaitForKey` until a key is pressed
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waited_key().is_none()
+            && matches!(
+                self.try_read_instruction(self.state.pc as usize),
+                Ok(Chip8Instruction::WaitForKey { .. })
+            )
+    }
+
+    /// Instruction executed by the last tick, or `None` if the last tick didn't execute anything
+    pub fn last_instruction(&self) -> Option<Chip8Instruction> {
+        self.last_instruction
+    }
+
+    /// Whether the instruction executed by the last tick was a `Draw` that erased a pixel
+    pub fn draw_collided(&self) -> bool {
+        self.draw_collided
+    }
+
+    /// Events emitted since the last `take_events`, in the order they happened
+    ///
+    /// Events from `tick_timers` are kept along with those from `tick`, so that a `SoundStopped`
+    /// isn't lost to the tick that follows it.
+    pub fn events(&self) -> &[Chip8Event] {
+        &self.events
+    }
+
+    /// Remove and return the events emitted since the last call
+    pub fn take_events(&mut self) -> Vec<Chip8Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn save_state(&self) -> Chip8SaveState {
+        Chip8SaveState {
+            state: self.state,
+            rng: self.rng.clone(),
+            timer_counter: self.timer_counter,
+            ticks: self.ticks,
+            scheduled_input: self.scheduled_input.clone(),
+            released_keys: self.released_keys,
+        }
+    }
+
+    /// Copy of the machine state alone, without timing, input or random number generator state
+    ///
+    /// Use `save_state` to capture everything needed to resume execution exactly.
+    pub fn snapshot(&self) -> Chip8InterpreterState {
+        self.state
+    }
+
+    /// Replace the machine state with one taken by `snapshot`
+    pub fn restore(&mut self, state: Chip8InterpreterState) {
+        self.state = state;
+        self.memory_changed();
+        self.random_replay.clear();
+        self.released_keys = 0;
+        self.last_instruction = None;
+        self.screen_dirty = true;
+        self.halted = false;
+    }
+
+    pub fn load_state(&mut self, save: Chip8SaveState) {
+        self.state = save.state;
+        self.memory_changed();
+        self.rng = save.rng;
+        self.timer_counter = save.timer_counter;
+        self.ticks = save.ticks;
+        self.scheduled_input = save.scheduled_input;
+        self.history.clear();
+        self.random_replay.clear();
+        self.resume_from_breakpoint = None;
+        self.halted = false;
+        self.released_keys = save.released_keys;
+        self.draw_collided = false;
+        self.last_instruction = None;
+        self.events.clear();
+        self.screen_dirty = true;
+    }
+
+    /// Save the current state together with the active configuration
+    pub fn save_bundle(&self) -> Chip8SaveBundle {
+        Chip8SaveBundle {
+            version: Chip8SaveBundle::VERSION,
+            config: self.config,
+            save: self.save_state(),
+        }
+    }
+
+    /// Restore both the configuration and the state from a bundle made by `save_bundle`
+    pub fn load_bundle(&mut self, bundle: Chip8SaveBundle) -> Result<(), Chip8InterpreterError> {
+        if bundle.version != Chip8SaveBundle::VERSION {
+            return Err(Chip8InterpreterError::UnsupportedSaveVersion(
+                bundle.version,
+            ));
+        }
+
+        self.config = bundle.config;
+        self.load_state(bundle.save);
+        Ok(())
+    }
+
+    /// Number of ticks since the last reset
+    ///
+    /// This counts every tick, including those spent stalled on `WaitForKey` or waiting for
+    /// vertical blank, so it is not the number of instructions executed.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Cycles the instructions executed since the last reset cost under `Chip8Config::cycle_costs`
+    ///
+    /// Stalled ticks execute no instruction and cost nothing. This is not part of save states.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Number of instructions left to execute before the delay and sound timers next count down
+    ///
+    /// This is `None` unless `tick` drives the timers.
+    pub fn instructions_until_timer_tick(&self) -> Option<usize> {
+        self.tick_drives_timers.then(|| {
+            self.timer_tick_interval()
+                .saturating_sub(self.timer_counter)
+                .max(1)
+        })
     }
 
     pub fn try_read_instruction(
@@ -108,77 +559,742 @@ impl Chip8Interpreter {
         }
         let opcode =
             ((self.state.memory[address] as u16) << 8) | (self.state.memory[address + 1] as u16);
-        Chip8Instruction::try_from(opcode)
+        let instruction = self.decode(opcode)?;
+        self.read_long_operand(address, instruction)
     }
 
+    /// Fill in the address of a `LoadLongIndex` at `address` from the word following its opcode
+    fn read_long_operand(
+        &self,
+        address: usize,
+        instruction: Chip8Instruction,
+    ) -> Result<Chip8Instruction, Chip8InterpreterError> {
+        match instruction {
+            Chip8Instruction::LoadLongIndex { .. } => {
+                match self.state.memory.get(address + 2..address + 4) {
+                    Some(&[hi, lo]) => Ok(Chip8Instruction::LoadLongIndex {
+                        address: u16::from_be_bytes([hi, lo]),
+                    }),
+                    _ => Err(Chip8InterpreterError::MemoryAccessError),
+                }
+            }
+            instruction => Ok(instruction),
+        }
+    }
+
+    /// Move PC past the next instruction, which takes two words if it is a `LoadLongIndex`
+    fn skip_next_instruction(&mut self) {
+        let next = self.state.pc as usize;
+        let long = self.config.variant.supports(InstructionSet::XoChip)
+            && self.state.memory.get(next..next + 2) == Some(&[0xf0, 0x00]);
+        self.state.pc += if long { 4 } else { 2 };
+    }
+
+    /// Decode the instruction at `address`, keeping the raw opcode alongside it
+    fn decode_at(&self, address: u16) -> DecodedInstruction {
+        let memory = &self.state.memory;
+        let opcode = match (
+            memory.get(address as usize),
+            memory.get(address as usize + 1),
+        ) {
+            (Some(&hi), Some(&lo)) => u16::from_be_bytes([hi, lo]),
+            _ => 0,
+        };
+
+        DecodedInstruction {
+            address,
+            opcode,
+            instruction: self.try_read_instruction(address as usize),
+        }
+    }
+
+    /// Decode an opcode, rejecting instructions the configured variant doesn't support
+    fn decode(&self, opcode: u16) -> Result<Chip8Instruction, Chip8InterpreterError> {
+        let instruction = Chip8Instruction::try_from(opcode)?;
+        if !self.config.variant.supports(instruction.instruction_set()) {
+            return Err(Chip8InterpreterError::InvalidInstruction(opcode));
+        }
+        Ok(instruction)
+    }
+
+    /// Load `rom` at the configured initial PC, see `Chip8Config::initial_pc`
     pub fn try_load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8InterpreterError> {
-        if rom.len() > MAX_ROM_SIZE as usize {
+        let address = self.config.initial_pc();
+        if address >= MEMORY_SIZE || !address.is_multiple_of(2) {
+            return Err(Chip8InterpreterError::InvalidInitialPc(address));
+        }
+        if rom.len() > self.max_rom_size() {
             return Err(Chip8InterpreterError::RomFileTooLarge);
         }
 
-        let mem =
-            &mut self.state.memory[(BASE_ADDRESS as usize)..(BASE_ADDRESS as usize + rom.len())];
+        let mem = &mut self.state.memory[(address as usize)..(address as usize + rom.len())];
         mem.copy_from_slice(rom);
+        self.rom = rom.to_vec();
+        // Flags belong to the ROM that stored them
+        self.state.rpl_flags = [0; RPL_FLAG_COUNT];
+        self.memory_changed();
+        Ok(())
+    }
+
+    /// Whether the font sprites at the start of memory still match `FONT_ROM`
+    ///
+    /// Anything overwriting this region breaks `SelectCharacter` without any other indication.
+    pub fn verify_font_intact(&self) -> bool {
+        self.state.memory[..FONT_ROM.len()] == FONT_ROM
+    }
+
+    /// Space available to a ROM loaded at the initial PC
+    fn max_rom_size(&self) -> usize {
+        MEMORY_SIZE.saturating_sub(self.config.initial_pc()) as usize
+    }
+
+    /// Contents of the currently loaded ROM as it was loaded
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    /// Reload the ROM after it has been modified
+    ///
+    /// If only data that the program can't execute changed, memory is patched in place and
+    /// execution continues. Otherwise the interpreter is reset and the new ROM loaded from scratch.
+    /// Returns whether the interpreter was reset.
+    pub fn reload_rom(&mut self, rom: &[u8]) -> Result<bool, Chip8InterpreterError> {
+        if rom.len() > self.max_rom_size() {
+            return Err(Chip8InterpreterError::RomFileTooLarge);
+        }
+
+        let base_address = self.config.initial_pc();
+
+        let code_changed = rom.len() != self.rom.len() || {
+            let program: Vec<_> = self.instructions().collect();
+            match lint::reachable_addresses(&program) {
+                Some(reachable) => {
+                    self.rom
+                        .iter()
+                        .zip(rom)
+                        .enumerate()
+                        .any(|(offset, (old, new))| {
+                            // Instructions are two bytes long, so the byte may be the low half of one
+                            let address = base_address + offset as u16;
+                            old != new
+                                && (reachable.contains(&address)
+                                    || reachable.contains(&(address - 1)))
+                        })
+                }
+                None => true,
+            }
+        };
+
+        if code_changed {
+            self.reset();
+            self.try_load_rom(rom)?;
+        } else {
+            for (offset, (old, &new)) in self.rom.iter().zip(rom).enumerate() {
+                if *old != new {
+                    self.state.memory[base_address as usize + offset] = new;
+                }
+            }
+            self.memory_changed();
+            self.rom = rom.to_vec();
+        }
+
+        Ok(code_changed)
+    }
+
+    /// FNV-1a hash of the currently loaded ROM
+    pub fn rom_hash(&self) -> u64 {
+        self.rom().iter().fold(0xcbf29ce484222325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    /// Decode every instruction in `start..end`, two bytes at a time
+    ///
+    /// `start` may be odd, to decode the instructions of programs that aren't aligned to even
+    /// addresses, the way the disassembly view does when it starts at 0001. Bytes that don't decode
+    /// are returned as errors along with their opcode rather than being skipped, so the listing
+    /// always has one entry per two bytes.
+    pub fn disassemble_range(&self, start: u16, end: u16) -> Vec<DecodedInstruction> {
+        (start..end)
+            .step_by(2)
+            .map(|address| self.decode_at(address))
+            .collect()
+    }
+
+    /// Iterate over the decoded instructions of the program, starting at the initial PC
+    ///
+    /// Iteration stops at the end of the loaded ROM, or at the end of memory if no ROM is loaded.
+    pub fn instructions(&self) -> impl Iterator<Item = DecodedInstruction> + '_ {
+        let start = self.config.initial_pc();
+        let end = if !self.rom.is_empty() {
+            start + self.rom.len() as u16
+        } else {
+            MEMORY_SIZE
+        };
+
+        (start..end)
+            .step_by(2)
+            .map(move |address| self.decode_at(address))
+    }
+
+    /// Run the default lint rules over the loaded program
+    pub fn lint(&self) -> Vec<LintFinding> {
+        let program: Vec<_> = self.instructions().collect();
+        lint::lint(&program, &lint::default_rules())
+    }
+
+    /// Write the loaded ROM as a JSON array of `DisassemblyEntry`
+    ///
+    /// `label_at` names addresses, such as the annotations the user made in the app.
+    pub fn write_disassembly_json<W: Write>(
+        &self,
+        writer: &mut W,
+        label_at: impl Fn(u16) -> Option<String>,
+    ) -> serde_json::Result<()> {
+        let start = self.config.initial_pc();
+        let end = start + self.rom.len() as u16;
+        let entries: Vec<DisassemblyEntry> = self
+            .disassemble_range(start, end)
+            .iter()
+            .map(|decoded| DisassemblyEntry {
+                label: label_at(decoded.address),
+                ..DisassemblyEntry::from(decoded)
+            })
+            .collect();
+        serde_json::to_writer_pretty(writer, &entries)
+    }
+
+    /// Write a listing of the loaded ROM with addresses, raw bytes and mnemonics
+    pub fn write_disassembly<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "; ROM hash: {:016x}", self.rom_hash())?;
+        writeln!(writer, "; Variant: {}", self.config.variant)?;
+        writeln!(writer, "; Size: {} bytes", self.rom.len())?;
+        writeln!(writer)?;
+
+        let start = self.config.initial_pc();
+        let end = start + self.rom.len() as u16;
+        for decoded in self.disassemble_range(start, end) {
+            match decoded.instruction {
+                Ok(instruction) => writeln!(
+                    writer,
+                    "{:04x}:  {:04x}  {}",
+                    decoded.address, decoded.opcode, instruction
+                )?,
+                Err(_) => writeln!(
+                    writer,
+                    "{:04x}:  {:04x}  ; data",
+                    decoded.address, decoded.opcode
+                )?,
+            }
+        }
+
         Ok(())
     }
 
     pub fn set_input_keys(&mut self, input_keys: u32) {
+        self.released_keys |= self.state.input_keys & !input_keys;
         self.state.input_keys = input_keys;
     }
 
+    /// Mark `key` as held down
+    pub fn press_key(&mut self, key: u8) -> Result<(), Chip8InterpreterError> {
+        if key > 0xf {
+            return Err(Chip8InterpreterError::InvalidInputKey(key));
+        }
+        self.state.input_keys |= 1u32 << key;
+        Ok(())
+    }
+
+    /// Mark `key` as no longer held down
+    pub fn release_key(&mut self, key: u8) -> Result<(), Chip8InterpreterError> {
+        if key > 0xf {
+            return Err(Chip8InterpreterError::InvalidInputKey(key));
+        }
+        self.released_keys |= self.state.input_keys & (1u32 << key);
+        self.state.input_keys &= !(1u32 << key);
+        Ok(())
+    }
+
+    /// Move execution to `address`, optionally discarding the call stack
+    ///
+    /// The address has to be even, and the whole instruction at it has to lie within memory.
+    pub fn set_pc(&mut self, address: u16, clear_stack: bool) -> Result<(), Chip8InterpreterError> {
+        if address >= MEMORY_SIZE - 1 {
+            return Err(Chip8InterpreterError::ProgramCounterOutOfBounds(address));
+        }
+        if !address.is_multiple_of(2) {
+            return Err(Chip8InterpreterError::UnalignedProgramCounter(address));
+        }
+
+        self.state.pc = address;
+        if clear_stack {
+            self.state.stack = [0; STACK_SIZE];
+            self.state.sp = 0;
+        }
+        Ok(())
+    }
+
+    pub fn set_register(
+        &mut self,
+        register: usize,
+        value: u8,
+    ) -> Result<(), Chip8InterpreterError> {
+        if register >= REGISTER_COUNT {
+            return Err(Chip8InterpreterError::InvalidRegister(register));
+        }
+
+        self.state.registers[register] = value;
+        Ok(())
+    }
+
+    pub fn write_memory(&mut self, address: u16, value: u8) -> Result<(), Chip8InterpreterError> {
+        if address >= MEMORY_SIZE {
+            return Err(Chip8InterpreterError::MemoryAccessError);
+        }
+
+        self.state.memory[address as usize] = value;
+        self.memory_changed();
+        Ok(())
+    }
+
+    /// Replace the held input keys with `keys` once the tick counter reaches `at_tick`
+    ///
+    /// This allows input driven programs to be run deterministically without a user interface.
+    /// Stalled ticks are counted, so input can be scheduled for a program waiting on `WaitForKey`.
+    pub fn schedule_input(&mut self, at_tick: u64, keys: u32) {
+        self.scheduled_input.insert(at_tick, keys);
+    }
+
+    fn apply_scheduled_input(&mut self) {
+        let pending = self.scheduled_input.split_off(&(self.ticks + 1));
+        if let Some((_, &keys)) = self.scheduled_input.iter().next_back() {
+            self.set_input_keys(keys);
+        }
+        self.scheduled_input = pending;
+    }
+
     pub fn tick(&mut self) -> Result<(), Chip8InterpreterError> {
+        self.step().map(|_| ())
+    }
+
+    /// Execute a single instruction like `tick`, returning the instruction that was executed
+    ///
+    /// While stalled on `WaitForKey`, or on a `Draw` waiting for vertical blank, this returns the
+    /// stalled instruction without advancing PC, `last_instruction` tells the two cases apart.
+    pub fn step(&mut self) -> Result<Chip8Instruction, Chip8InterpreterError> {
+        if self.history_depth == 0 {
+            return self.execute();
+        }
+
+        let before = self.state;
+        self.random_drawn = None;
+        let result = self.execute();
+        // Instructions stopped by a watchpoint have still been executed
+        if let Err(e) = &result {
+            if !matches!(e, Chip8InterpreterError::WatchpointHit { .. }) {
+                return result;
+            }
+        }
+
+        if self.history.len() == self.history_depth {
+            self.history.pop_front();
+        }
+        let mut delta = StateDelta::between(&before, &self.state);
+        delta.random = self.random_drawn.take();
+        self.history.push_back(delta);
+        result
+    }
+
+    /// Keep the changes made by the last `depth` ticks so that `step_back` can undo them
+    ///
+    /// Only what a tick changed is kept, usually around 150 bytes, so even deep histories are
+    /// cheap. A tick that clears the screen or stores many bytes takes more. The default depth is
+    /// 0, which keeps no history.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history_depth = depth;
+        while self.history.len() > depth {
+            self.history.pop_front();
+        }
+        // Without history nothing can be undone, so nothing is left to replay either
+        if depth == 0 {
+            self.random_replay.clear();
+        }
+    }
+
+    pub fn history_depth(&self) -> usize {
+        self.history_depth
+    }
+
+    /// Number of ticks that `step_back` can currently undo
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Return to the state from before the most recent tick kept in the history
+    ///
+    /// Only the machine state is restored, the tick counter and timer scheduling keep running
+    /// forward. A `Random` that is undone draws the same byte when it is executed again.
+    pub fn step_back(&mut self) -> Result<(), Chip8InterpreterError> {
+        let delta = self
+            .history
+            .pop_back()
+            .ok_or(Chip8InterpreterError::HistoryEmpty)?;
+        delta.revert(&mut self.state);
+        if delta.changes_memory() {
+            self.memory_changed();
+        }
+        self.random_replay.extend(delta.random);
+        self.released_keys = 0;
+        self.last_instruction = None;
+        self.screen_dirty = true;
+        self.halted = false;
+        Ok(())
+    }
+
+    /// Next random byte for `Random`, replaying bytes of undone instructions first
+    fn draw_random(&mut self) -> u8 {
+        let byte = self
+            .random_replay
+            .pop()
+            .unwrap_or_else(|| self.rng.next_u8());
+        self.random_drawn = Some(byte);
+        byte
+    }
+
+    fn execute(&mut self) -> Result<Chip8Instruction, Chip8InterpreterError> {
+        self.apply_scheduled_input();
+        self.draw_collided = false;
+        self.last_instruction = None;
+        self.watchpoint_hit = None;
+
+        if self.halted {
+            return Ok(Chip8Instruction::Exit);
+        }
+
+        // Ticking again after hitting a breakpoint resumes from it
+        let pc = self.state.pc;
+        if self.breakpoints.contains(&pc) && self.resume_from_breakpoint != Some(pc) {
+            self.resume_from_breakpoint = Some(pc);
+            self.events.push(Chip8Event::BreakpointHit(pc));
+            return Err(Chip8InterpreterError::BreakpointHit(pc));
+        }
+
         if (self.state.pc + 1) >= MEMORY_SIZE {
             return Err(Chip8InterpreterError::ProgramCounterOutOfBounds(
                 self.state.pc,
             ));
         }
 
-        // If next instruction is WaitForKey we can only continue if we have input
         let opcode = ((self.state.memory[self.state.pc as usize] as u16) << 8)
             | (self.state.memory[self.state.pc as usize + 1] as u16);
-        let instruction = Chip8Instruction::try_from(opcode)?;
+
+        // Zeroed memory decodes to a run of no-ops, so skip straight past decode and dispatch
+        if opcode == 0x0000 {
+            if self.config.trap_uninitialized_memory {
+                return Err(Chip8InterpreterError::UninitializedMemory(self.state.pc));
+            }
+
+            self.state.pc += 2;
+            self.last_instruction = Some(Chip8Instruction::NoOperation);
+            self.resume_from_breakpoint = None;
+            self.released_keys = 0;
+            self.ticks += 1;
+            self.cycles += self.config.cycle_costs.cost(&Chip8Instruction::NoOperation) as u64;
+            self.update_timers();
+            return Ok(Chip8Instruction::NoOperation);
+        }
+
+        // If next instruction is WaitForKey we can only continue if we have input
+        let instruction = match self
+            .decode(opcode)
+            .and_then(|instruction| self.read_long_operand(self.state.pc as usize, instruction))
+        {
+            Ok(instruction) => instruction,
+            Err(e) => {
+                self.events.push(Chip8Event::InvalidOpcode {
+                    address: self.state.pc,
+                    opcode,
+                });
+                return Err(e);
+            }
+        };
         if let Chip8Instruction::WaitForKey { .. } = instruction {
-            if self.state.input_keys == 0 {
-                return Ok(());
+            if self.waited_key().is_none() {
+                self.ticks += 1;
+                self.update_timers();
+                return Ok(instruction);
+            }
+        }
+        if let Chip8Instruction::Draw { .. } = instruction {
+            if self.draw_waits_for_vblank() && !self.vblank {
+                self.ticks += 1;
+                self.update_timers();
+                return Ok(instruction);
             }
         }
 
         // Instruction preconditions have been met
+        let address = self.state.pc;
         self.state.pc += 2;
-        self.dispatch(instruction)?;
+        if let Err(e) = self.dispatch(instruction) {
+            // Leave PC on the faulting instruction so that it is the one the debugger shows
+            self.state.pc = address;
+            match e {
+                Chip8InterpreterError::CallStackDepthExceeded { .. } => {
+                    self.events.push(Chip8Event::StackOverflow(address))
+                }
+                Chip8InterpreterError::CallStackEmpty { .. } => {
+                    self.events.push(Chip8Event::StackUnderflow(address))
+                }
+                _ => {}
+            }
+            return Err(e);
+        }
+        self.last_instruction = Some(instruction);
+        self.resume_from_breakpoint = None;
+        self.released_keys = 0;
+        self.ticks += 1;
+        self.cycles += self.config.cycle_costs.cost(&instruction) as u64;
 
         self.update_timers();
 
+        if let Some((address, value)) = self.watchpoint_hit.take() {
+            return Err(Chip8InterpreterError::WatchpointHit { address, value });
+        }
+
+        Ok(instruction)
+    }
+
+    /// Tick `n` times, stopping at the first error
+    pub fn run_cycles(&mut self, n: usize) -> Result<(), Chip8InterpreterError> {
+        for _ in 0..n {
+            self.tick()?;
+        }
         Ok(())
     }
 
-    fn update_timers(&mut self) {
-        self.timer_counter += 1;
-        if self.timer_counter >= TIMER_TICK_INTERVAL {
-            self.timer_counter = 0;
+    /// Run one frame of `ticks_per_frame` ticks and then count the timers down once
+    ///
+    /// This is the whole per-frame loop for hosts that don't need the debugging aids of the app.
+    /// The timers are only counted down by the frame, whatever `set_tick_drives_timers` says, and
+    /// not at all if a tick fails.
+    pub fn run_frame(&mut self, ticks_per_frame: usize) -> Result<(), Chip8InterpreterError> {
+        let tick_drives_timers = self.tick_drives_timers;
+        self.tick_drives_timers = false;
+        let result = self.run_cycles(ticks_per_frame);
+        self.tick_drives_timers = tick_drives_timers;
+        result?;
 
-            if self.state.st > 0 {
-                self.state.st -= 1;
-            }
+        self.tick_timers();
+        Ok(())
+    }
+
+    /// Run until the current subroutine returns to its caller
+    ///
+    /// Returns `NotInSubroutine` if the call stack is empty, and `ExecutionLimitReached` if the
+    /// subroutine doesn't return within `max_ticks` ticks. Stops early if the program exits.
+    pub fn step_out(&mut self, max_ticks: usize) -> Result<(), Chip8InterpreterError> {
+        let sp = self.state.sp;
+        if sp == 0 {
+            return Err(Chip8InterpreterError::NotInSubroutine { pc: self.state.pc });
+        }
 
-            if self.state.dt > 0 {
-                self.state.dt -= 1;
+        for _ in 0..max_ticks {
+            self.tick()?;
+            if self.state.sp < sp || self.halted {
+                return Ok(());
             }
         }
+        Err(Chip8InterpreterError::ExecutionLimitReached {
+            cycles: max_ticks as u64,
+        })
     }
 
-    fn dispatch(&mut self, instruction: Chip8Instruction) -> Result<(), Chip8InterpreterError> {
-        match instruction {
-            Chip8Instruction::NoOperation => Ok(()),
-            Chip8Instruction::Syscall { .. } => Ok(()),
-            Chip8Instruction::Random { register, mask } => {
-                self.state.registers[register] = rand::random::<u8>() & mask;
-                Ok(())
+    /// Run until the program parks itself in a jump to its own address or executes `Exit`
+    ///
+    /// Returns `ExecutionLimitReached` if that doesn't happen within `max_cycles` cycles, or before
+    /// `timeout` elapses, so that runs can be safely automated. Input can be fed in advance with
+    /// `schedule_input`, `on_wait_for_key` decides what happens when the program waits for a key
+    /// beyond that.
+    ///
+    /// Both ways of finishing return `Ok`, `is_halted` tells them apart. Idle loops of more than
+    /// one instruction aren't detected and run into the limits. Events are discarded as it runs so
+    /// that long runs don't pile them up.
+    pub fn run_headless(
+        &mut self,
+        max_cycles: u64,
+        timeout: Option<Duration>,
+        on_wait_for_key: HeadlessKeyPolicy,
+    ) -> Result<(), Chip8InterpreterError> {
+        if let HeadlessKeyPolicy::Press(key) = on_wait_for_key {
+            if key > 0xf {
+                return Err(Chip8InterpreterError::InvalidInputKey(key));
             }
+        }
 
-            Chip8Instruction::Call { address } => {
+        let start_time = Instant::now();
+        let start_ticks = self.ticks;
+
+        loop {
+            if self.is_waiting_for_key() && self.scheduled_input.is_empty() {
+                match on_wait_for_key {
+                    HeadlessKeyPolicy::Stall => {}
+                    HeadlessKeyPolicy::Press(key) => {
+                        // Release on the following tick so that both `WaitForKey` quirks complete
+                        self.schedule_input(self.ticks, 1 << key);
+                        self.schedule_input(self.ticks + 1, 0);
+                    }
+                    HeadlessKeyPolicy::Stop => {
+                        return Err(Chip8InterpreterError::WaitingForKey { pc: self.state.pc });
+                    }
+                }
+            }
+
+            let pc = self.state.pc;
+            self.events.clear();
+            self.tick()?;
+            if self.halted {
+                return Ok(());
+            }
+
+            if let Some(Chip8Instruction::Jump { address }) = self.last_instruction {
+                if address == pc {
+                    return Ok(());
+                }
+            }
+
+            let elapsed_cycles = self.ticks - start_ticks;
+            let timed_out = elapsed_cycles.is_multiple_of(WATCHDOG_CHECK_INTERVAL)
+                && timeout.is_some_and(|timeout| start_time.elapsed() >= timeout);
+            if elapsed_cycles >= max_cycles || timed_out {
+                return Err(Chip8InterpreterError::ExecutionLimitReached {
+                    cycles: elapsed_cycles,
+                });
+            }
+        }
+    }
+
+    /// Number of ticks between timer decrements at the configured clock speed
+    fn timer_tick_interval(&self) -> usize {
+        (self.config.clock_hz() / TIMER_FREQUENCY).max(1) as usize
+    }
+
+    /// Counter that changes whenever memory is written or the set of decodable opcodes changes
+    pub fn memory_generation(&self) -> u64 {
+        self.memory_generation
+    }
+
+    fn memory_changed(&mut self) {
+        self.memory_generation = self.memory_generation.wrapping_add(1);
+    }
+
+    /// Key that `WaitForKey` would store right now, if any
+    ///
+    /// When several keys qualify the lowest one wins. Normally that is the lowest held key, with
+    /// `wait_for_key_release` it is the lowest key released since the previous instruction, so a
+    /// key has to be pressed and released while waiting.
+    fn waited_key(&self) -> Option<u8> {
+        let keys = if self.config.wait_for_key_release {
+            self.released_keys
+        } else {
+            self.state.input_keys
+        };
+        (keys != 0).then(|| keys.trailing_zeros() as u8)
+    }
+
+    /// Count the delay and sound timers down once
+    ///
+    /// Call this at `TIMER_FREQUENCY` when `tick` doesn't drive the timers. Every call also marks a
+    /// vertical blank for draws held back by `Quirks::display_wait`.
+    pub fn tick_timers(&mut self) {
+        self.vblank = true;
+        if self.timers_frozen {
+            return;
+        }
+
+        if self.state.st > 0 {
+            self.state.st -= 1;
+            if self.state.st == 0 {
+                self.events.push(Chip8Event::SoundStopped);
+            }
+        }
+
+        if self.state.dt > 0 {
+            self.state.dt -= 1;
+        }
+    }
+
+    /// Write a byte of memory on behalf of an instruction, noting writes to watched addresses
+    ///
+    /// The address must have been bounds checked already.
+    fn write_mem(&mut self, address: u16, value: u8) {
+        self.state.memory[address as usize] = value;
+        if self.watchpoint_hit.is_none() && self.watchpoints.contains(&address) {
+            self.watchpoint_hit = Some((address, value));
+        }
+    }
+
+    /// Turn off every pixel of the display planes in `planes`
+    fn clear_screen(&mut self, planes: u8) {
+        if self.capture_cleared_screen {
+            self.last_cleared_screen = Some(self.state.screen);
+        }
+        for pixel in self.state.screen.iter_mut().flatten() {
+            *pixel &= !planes;
+        }
+        self.screen_dirty = true;
+        self.events.push(Chip8Event::ScreenCleared);
+    }
+
+    /// Put back the planes that aren't selected from `previous` after scrolling the whole screen
+    fn keep_unselected_planes(&mut self, previous: &[[u8; SCREEN_WIDTH]; SCREEN_HEIGHT]) {
+        let mask = self.state.plane_mask;
+        for (row, previous_row) in self.state.screen.iter_mut().zip(previous) {
+            for (pixel, &previous_pixel) in row.iter_mut().zip(previous_row) {
+                *pixel = (*pixel & mask) | (previous_pixel & !mask);
+            }
+        }
+        self.screen_dirty = true;
+    }
+
+    /// Whether draws currently have to wait for vertical blank, see `Quirks::display_wait`
+    fn draw_waits_for_vblank(&self) -> bool {
+        let quirks = self.config.quirks;
+        quirks.display_wait && !(quirks.display_wait_lowres_only && self.state.hires)
+    }
+
+    fn update_timers(&mut self) {
+        // Frozen timers are left alone by `tick_timers`, vertical blank still has to come around
+        if !self.tick_drives_timers {
+            return;
+        }
+
+        self.timer_counter += 1;
+        if self.timer_counter >= self.timer_tick_interval() {
+            self.timer_counter = 0;
+            self.tick_timers();
+        }
+    }
+
+    fn dispatch(&mut self, instruction: Chip8Instruction) -> Result<(), Chip8InterpreterError> {
+        match instruction {
+            Chip8Instruction::NoOperation => Ok(()),
+            Chip8Instruction::Exit => {
+                self.halted = true;
+                Ok(())
+            }
+            Chip8Instruction::Syscall { .. } => Ok(()),
+            Chip8Instruction::Random { register, mask } => {
+                self.state.registers[register] = self.draw_random() & mask;
+                Ok(())
+            }
+
+            Chip8Instruction::Call { address } => {
                 if self.state.sp > (STACK_SIZE - 1) {
-                    return Err(Chip8InterpreterError::CallStackDepthExceeded);
+                    return Err(Chip8InterpreterError::CallStackDepthExceeded {
+                        pc: self.state.pc - 2,
+                        depth: self.state.sp,
+                    });
                 }
 
                 self.state.stack[self.state.sp] = self.state.pc;
@@ -188,7 +1304,9 @@ impl Chip8Interpreter {
             }
             Chip8Instruction::Return => {
                 if self.state.sp == 0 {
-                    return Err(Chip8InterpreterError::CallStackEmpty);
+                    return Err(Chip8InterpreterError::CallStackEmpty {
+                        pc: self.state.pc - 2,
+                    });
                 }
 
                 self.state.sp -= 1;
@@ -196,15 +1314,20 @@ impl Chip8Interpreter {
                 Ok(())
             }
             Chip8Instruction::StoreRegisters { count } => {
+                // The last byte written is I + count - 1, so I + count may equal MEMORY_SIZE
                 let mut cursor = self.state.i as usize;
                 if (cursor + count) > MEMORY_SIZE.into() {
                     return Err(Chip8InterpreterError::MemoryAccessError);
                 }
 
                 for i in 0..count {
-                    self.state.memory[cursor] = self.state.registers[i];
+                    self.write_mem(cursor as u16, self.state.registers[i]);
                     cursor += 1;
                 }
+                if self.config.quirks.memory_increments_i {
+                    self.state.i = cursor as u16;
+                }
+                self.memory_changed();
                 Ok(())
             }
             Chip8Instruction::LoadRegisters { count } => {
@@ -217,6 +1340,47 @@ impl Chip8Interpreter {
                     self.state.registers[i] = self.state.memory[cursor as usize];
                     cursor += 1;
                 }
+                if self.config.quirks.memory_increments_i {
+                    self.state.i = cursor as u16;
+                }
+                Ok(())
+            }
+            // X can name more registers than there are flags, the rest are ignored
+            Chip8Instruction::StoreFlags { count } => {
+                let count = count.min(RPL_FLAG_COUNT);
+                self.state.rpl_flags[..count].copy_from_slice(&self.state.registers[..count]);
+                Ok(())
+            }
+            Chip8Instruction::LoadFlags { count } => {
+                let count = count.min(RPL_FLAG_COUNT);
+                self.state.registers[..count].copy_from_slice(&self.state.rpl_flags[..count]);
+                Ok(())
+            }
+            // Unlike FX55 and FX65 these never change I
+            Chip8Instruction::StoreRegisterRange { x, y } => {
+                let count = x.abs_diff(y) + 1;
+                if (self.state.i as usize + count) > MEMORY_SIZE.into() {
+                    return Err(Chip8InterpreterError::MemoryAccessError);
+                }
+
+                for offset in 0..count {
+                    let register = if x <= y { x + offset } else { x - offset };
+                    self.write_mem(self.state.i + offset as u16, self.state.registers[register]);
+                }
+                self.memory_changed();
+                Ok(())
+            }
+            Chip8Instruction::LoadRegisterRange { x, y } => {
+                let count = x.abs_diff(y) + 1;
+                if (self.state.i as usize + count) > MEMORY_SIZE.into() {
+                    return Err(Chip8InterpreterError::MemoryAccessError);
+                }
+
+                for offset in 0..count {
+                    let register = if x <= y { x + offset } else { x - offset };
+                    self.state.registers[register] =
+                        self.state.memory[self.state.i as usize + offset];
+                }
                 Ok(())
             }
 
@@ -234,7 +1398,47 @@ impl Chip8Interpreter {
             }
 
             Chip8Instruction::ClearScreen => {
-                self.state.screen = [[0; SCREEN_WIDTH]; SCREEN_HEIGHT];
+                self.clear_screen(self.state.plane_mask);
+                Ok(())
+            }
+            Chip8Instruction::LowResolution | Chip8Instruction::HighResolution => {
+                self.state.hires = matches!(instruction, Chip8Instruction::HighResolution);
+                if self.config.quirks.resolution_switch_clears_screen {
+                    self.clear_screen(ALL_PLANES);
+                }
+                Ok(())
+            }
+            // Scroll amounts are given in high resolution pixels, and the screen is always in low
+            // resolution, so they are halved
+            Chip8Instruction::ScrollDown { amount } => {
+                let previous = self.state.screen;
+                let rows = amount / 2;
+                let screen = &mut self.state.screen;
+                screen.copy_within(..SCREEN_HEIGHT - rows, rows);
+                screen[..rows].fill([0; SCREEN_WIDTH]);
+                self.keep_unselected_planes(&previous);
+                Ok(())
+            }
+            Chip8Instruction::ScrollRight => {
+                let previous = self.state.screen;
+                for row in self.state.screen.iter_mut() {
+                    row.copy_within(..SCREEN_WIDTH - 2, 2);
+                    row[..2].fill(0);
+                }
+                self.keep_unselected_planes(&previous);
+                Ok(())
+            }
+            Chip8Instruction::ScrollLeft => {
+                let previous = self.state.screen;
+                for row in self.state.screen.iter_mut() {
+                    row.copy_within(2.., 0);
+                    row[SCREEN_WIDTH - 2..].fill(0);
+                }
+                self.keep_unselected_planes(&previous);
+                Ok(())
+            }
+            Chip8Instruction::SelectPlane { mask } => {
+                self.state.plane_mask = mask & ALL_PLANES;
                 Ok(())
             }
             Chip8Instruction::SelectCharacter { register } => {
@@ -242,61 +1446,102 @@ impl Chip8Interpreter {
                 Ok(())
             }
             Chip8Instruction::StoreBcd { register } => {
-                if (self.state.i + 3) > MEMORY_SIZE {
+                if (self.state.i as usize + 3) > MEMORY_SIZE.into() {
                     return Err(Chip8InterpreterError::MemoryAccessError);
                 }
 
-                self.state.memory[self.state.i as usize] = self.state.registers[register] / 100;
-                self.state.memory[self.state.i as usize + 1] =
-                    (self.state.registers[register] / 10) % 10;
-                self.state.memory[self.state.i as usize + 2] = self.state.registers[register] % 10;
+                let value = self.state.registers[register];
+                self.write_mem(self.state.i, value / 100);
+                self.write_mem(self.state.i + 1, (value / 10) % 10);
+                self.write_mem(self.state.i + 2, value % 10);
+                self.memory_changed();
                 Ok(())
             }
             Chip8Instruction::Draw { x, y, len } => {
-                let pos_x = self.state.registers[x] as usize;
-                let pos_y = self.state.registers[y] as usize;
+                // The start position always wraps, the rest of the sprite wraps or gets clipped
+                let pos_x = self.state.registers[x] as usize % SCREEN_WIDTH;
+                let pos_y = self.state.registers[y] as usize % SCREEN_HEIGHT;
+                let clip = self.config.quirks.clip_sprites;
+
+                // SUPER-CHIP draws a 16x16 sprite of two bytes per row for a length of 0 in hires
+                let large = len == 0
+                    && self.state.hires
+                    && self.config.variant.supports(InstructionSet::SuperChip);
+                let (rows, row_bytes) = if large { (16, 2) } else { (len, 1) };
+                let width = row_bytes * 8;
 
+                let planes = (self.state.plane_mask & ALL_PLANES).count_ones() as usize;
+                if self.state.i as usize + planes * rows * row_bytes > MEMORY_SIZE.into() {
+                    return Err(Chip8InterpreterError::MemoryAccessError);
+                }
+
+                // Every selected plane takes its own sprite data, one after the other
+                let mut sprite_address = self.state.i as usize;
                 let mut set_flag = false;
-                for sprite_row_index in 0..len {
-                    let sprite_row = self.state.memory[self.state.i as usize + sprite_row_index];
-
-                    let pixel_pos_y = (pos_y + sprite_row_index) % SCREEN_HEIGHT;
-                    let screen_line = &mut self.state.screen[pixel_pos_y];
-                    for i in 0..8 {
-                        let pixel_pos_x = (pos_x + 7 - i) % SCREEN_WIDTH;
-                        let old_val = screen_line[pixel_pos_x];
-                        screen_line[pixel_pos_x] ^= (sprite_row >> i) & 1;
-
-                        if old_val > 0 && screen_line[pixel_pos_x] == 0 {
-                            set_flag = true;
+                for plane in 0..PLANE_COUNT {
+                    let plane_bit = 1 << plane;
+                    if self.state.plane_mask & plane_bit == 0 {
+                        continue;
+                    }
+
+                    for sprite_row_index in 0..rows {
+                        let row_address = sprite_address + sprite_row_index * row_bytes;
+                        let sprite_row = self.state.memory[row_address..row_address + row_bytes]
+                            .iter()
+                            .fold(0u16, |row, &byte| (row << 8) | byte as u16);
+
+                        let pixel_pos_y = pos_y + sprite_row_index;
+                        if clip && pixel_pos_y >= SCREEN_HEIGHT {
+                            break;
+                        }
+                        let screen_line = &mut self.state.screen[pixel_pos_y % SCREEN_HEIGHT];
+                        for i in 0..width {
+                            let pixel_pos_x = pos_x + width - 1 - i;
+                            if clip && pixel_pos_x >= SCREEN_WIDTH {
+                                continue;
+                            }
+                            if (sprite_row >> i) & 1 == 0 {
+                                continue;
+                            }
+
+                            let pixel = &mut screen_line[pixel_pos_x % SCREEN_WIDTH];
+                            set_flag |= *pixel & plane_bit != 0;
+                            *pixel ^= plane_bit;
                         }
                     }
+                    sprite_address += rows * row_bytes;
                 }
                 self.state.registers[15] = if set_flag { 1 } else { 0 };
+                self.draw_collided = set_flag;
+                self.vblank = false;
+                if set_flag {
+                    self.events.push(Chip8Event::Collision);
+                }
+                self.screen_dirty = true;
                 Ok(())
             }
 
             Chip8Instruction::SkipIfEqualValue { register, value } => {
                 if self.state.registers[register] == value {
-                    self.state.pc += 2;
+                    self.skip_next_instruction();
                 }
                 Ok(())
             }
             Chip8Instruction::SkipIfEqualRegister { x, y } => {
                 if self.state.registers[x] == self.state.registers[y] {
-                    self.state.pc += 2;
+                    self.skip_next_instruction();
                 }
                 Ok(())
             }
             Chip8Instruction::SkipIfNotEqualValue { register, value } => {
                 if self.state.registers[register] != value {
-                    self.state.pc += 2;
+                    self.skip_next_instruction();
                 }
                 Ok(())
             }
             Chip8Instruction::SkipIfNotEqualRegister { x, y } => {
                 if self.state.registers[x] != self.state.registers[y] {
-                    self.state.pc += 2;
+                    self.skip_next_instruction();
                 }
                 Ok(())
             }
@@ -307,7 +1552,7 @@ impl Chip8Interpreter {
                     ));
                 }
                 if self.state.input_keys & (1u32 << self.state.registers[register]) > 0 {
-                    self.state.pc += 2;
+                    self.skip_next_instruction();
                 }
                 Ok(())
             }
@@ -318,7 +1563,7 @@ impl Chip8Interpreter {
                     ));
                 }
                 if self.state.input_keys & (1u32 << self.state.registers[register]) == 0 {
-                    self.state.pc += 2;
+                    self.skip_next_instruction();
                 }
                 Ok(())
             }
@@ -327,6 +1572,11 @@ impl Chip8Interpreter {
                 self.state.i = address;
                 Ok(())
             }
+            Chip8Instruction::LoadLongIndex { address } => {
+                self.state.i = address;
+                self.state.pc += 2;
+                Ok(())
+            }
             Chip8Instruction::AddIndex { register } => {
                 self.state.i = self
                     .state
@@ -352,27 +1602,26 @@ impl Chip8Interpreter {
                 Ok(())
             }
             Chip8Instruction::SetSoundTimer { register } => {
+                let was_playing = self.should_buzz();
                 self.state.st = self.state.registers[register];
+                match (was_playing, self.should_buzz()) {
+                    (false, true) => self.events.push(Chip8Event::SoundStarted),
+                    (true, false) => self.events.push(Chip8Event::SoundStopped),
+                    _ => {}
+                }
                 Ok(())
             }
-            Chip8Instruction::WaitForKey { register } => {
-                if self.state.input_keys == 0 {
-                    Err(Chip8InterpreterError::ExpectingInputKey)
-                } else {
-                    for i in 0..16 {
-                        if (self.state.input_keys & (1u32 << i)) > 0 {
-                            self.state.registers[register] = i;
-                            break;
-                        }
-                    }
+            Chip8Instruction::WaitForKey { register } => match self.waited_key() {
+                Some(key) => {
+                    self.state.registers[register] = key;
                     Ok(())
                 }
-            }
+                None => Err(Chip8InterpreterError::ExpectingInputKey),
+            },
 
             Chip8Instruction::AddValue { register, value } => {
-                let (sum, carry) = self.state.registers[register].overflowing_add(value);
-                self.state.registers[register] = sum;
-                self.state.registers[15] = if carry { 0 } else { 1 };
+                // Unlike 8XY4, 7XNN leaves VF alone
+                self.state.registers[register] = self.state.registers[register].wrapping_add(value);
                 Ok(())
             }
             Chip8Instruction::AddRegister { x, y } => {
@@ -408,13 +1657,19 @@ impl Chip8Interpreter {
                 self.state.registers[x] = self.state.registers[x] ^ self.state.registers[y];
                 Ok(())
             }
-            Chip8Instruction::ShiftRight { x, .. } => {
+            Chip8Instruction::ShiftRight { x, y } => {
+                if self.config.quirks.shift_uses_vy {
+                    self.state.registers[x] = self.state.registers[y];
+                }
                 let carry = self.state.registers[x] & 1;
                 self.state.registers[x] = self.state.registers[x] >> 1;
                 self.state.registers[15] = carry;
                 Ok(())
             }
-            Chip8Instruction::ShiftLeft { x, .. } => {
+            Chip8Instruction::ShiftLeft { x, y } => {
+                if self.config.quirks.shift_uses_vy {
+                    self.state.registers[x] = self.state.registers[y];
+                }
                 let carry = self.state.registers[x] >> 7;
                 self.state.registers[x] = self.state.registers[x] << 1;
                 self.state.registers[15] = carry;
@@ -423,3 +1678,1463 @@ impl Chip8Interpreter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interpreter(variant: Chip8Variant, rom: &[u8]) -> Chip8Interpreter {
+        let config = Chip8Config {
+            variant,
+            ..Default::default()
+        };
+        interpreter_with_config(config, rom)
+    }
+
+    fn interpreter_with_config(config: Chip8Config, rom: &[u8]) -> Chip8Interpreter {
+        let mut interp = Chip8Interpreter::with_seed(0);
+        interp.set_config(config);
+        interp.reset();
+        interp.try_load_rom(rom).unwrap();
+        interp
+    }
+
+    fn lit_pixels(interp: &Chip8Interpreter) -> usize {
+        interp
+            .state()
+            .screen
+            .iter()
+            .flatten()
+            .filter(|&&pixel| pixel != 0)
+            .count()
+    }
+
+    #[test]
+    fn large_sprite_draws_in_hires() {
+        let mut rom = vec![0x00, 0xff, 0xa2, 0x0a, 0xd0, 0x10, 0xd0, 0x10, 0x12, 0x08];
+        rom.extend([0xff; 32]);
+        let mut interp = interpreter(Chip8Variant::SuperChip, &rom);
+
+        interp.run_cycles(3).unwrap();
+        assert_eq!(lit_pixels(&interp), 256);
+        assert_eq!(interp.state().registers[15], 0);
+
+        interp.tick().unwrap();
+        assert_eq!(lit_pixels(&interp), 0);
+        assert_eq!(interp.state().registers[15], 1);
+    }
+
+    #[test]
+    fn zero_length_sprite_draws_nothing_in_lores() {
+        let mut rom = vec![0xa2, 0x06, 0xd0, 0x10, 0x12, 0x04];
+        rom.extend([0xff; 32]);
+        let mut interp = interpreter(Chip8Variant::SuperChip, &rom);
+
+        interp.run_cycles(2).unwrap();
+        assert_eq!(lit_pixels(&interp), 0);
+    }
+
+    #[test]
+    fn sprite_past_end_of_memory_is_rejected() {
+        let mut interp = interpreter(
+            Chip8Variant::SuperChip,
+            &[0x00, 0xff, 0xaf, 0xf0, 0xd0, 0x10],
+        );
+
+        interp.run_cycles(2).unwrap();
+        assert!(matches!(
+            interp.tick(),
+            Err(Chip8InterpreterError::MemoryAccessError)
+        ));
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 4);
+    }
+
+    #[test]
+    fn sprites_for_every_plane_must_fit_in_memory() {
+        // Two planes of 15 rows from 0xFE4 need 30 bytes, two more than are left
+        let rom = [0xf3, 0x01, 0xaf, 0xe4, 0xd0, 0x1f];
+        let mut interp = interpreter(Chip8Variant::XoChip, &rom);
+
+        interp.run_cycles(2).unwrap();
+        assert!(matches!(
+            interp.tick(),
+            Err(Chip8InterpreterError::MemoryAccessError)
+        ));
+    }
+
+    #[test]
+    fn add_value_leaves_vf_untouched() {
+        let mut interp = interpreter(
+            Chip8Variant::ChipModern,
+            &[0x6f, 0x42, 0x70, 0xff, 0x70, 0x01],
+        );
+
+        interp.run_cycles(2).unwrap();
+        assert_eq!(interp.state().registers[0], 0xff);
+        assert_eq!(interp.state().registers[15], 0x42);
+
+        interp.tick().unwrap();
+        assert_eq!(interp.state().registers[0], 0x00);
+        assert_eq!(interp.state().registers[15], 0x42);
+    }
+
+    #[test]
+    fn rom_starts_at_custom_initial_pc() {
+        let config = Chip8Config {
+            initial_pc: Some(0x600),
+            ..Default::default()
+        };
+        let mut interp = interpreter_with_config(config, &[0x6a, 0x2b]);
+        assert_eq!(interp.state().pc, 0x600);
+        assert_eq!(interp.state().memory[0x600..0x602], [0x6a, 0x2b]);
+        assert_eq!(interp.state().memory[BASE_ADDRESS as usize], 0);
+
+        interp.tick().unwrap();
+        assert_eq!(interp.state().registers[0xa], 0x2b);
+        assert_eq!(interp.state().pc, 0x602);
+    }
+
+    #[test]
+    fn odd_initial_pc_is_rejected() {
+        let mut interp = Chip8Interpreter::new();
+        interp.set_config(Chip8Config {
+            initial_pc: Some(0x601),
+            ..Default::default()
+        });
+        assert!(matches!(
+            interp.try_load_rom(&[0x6a, 0x2b]),
+            Err(Chip8InterpreterError::InvalidInitialPc(0x601))
+        ));
+    }
+
+    fn store_three_registers(memory_increments_i: bool) -> Chip8Interpreter {
+        let config = Chip8Config {
+            quirks: Quirks {
+                memory_increments_i,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rom = [0xa3, 0x00, 0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xf2, 0x55];
+        let mut interp = interpreter_with_config(config, &rom);
+        interp.run_cycles(5).unwrap();
+        assert_eq!(interp.state().memory[0x300..0x304], [1, 2, 3, 0]);
+        interp
+    }
+
+    #[test]
+    fn store_registers_leaves_i_unchanged_without_quirk() {
+        assert_eq!(store_three_registers(false).state().i, 0x300);
+    }
+
+    #[test]
+    fn store_registers_advances_i_with_quirk() {
+        assert_eq!(store_three_registers(true).state().i, 0x303);
+    }
+
+    #[test]
+    fn load_registers_advances_i_only_with_quirk() {
+        for (memory_increments_i, i) in [(false, 0x000), (true, 0x003)] {
+            let config = Chip8Config {
+                quirks: Quirks {
+                    memory_increments_i,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            // I starts out at the font, whose first bytes are 0xF0 0x90 0x90
+            let mut interp = interpreter_with_config(config, &[0xf2, 0x65]);
+            interp.tick().unwrap();
+            assert_eq!(interp.state().registers[..4], [0xf0, 0x90, 0x90, 0x00]);
+            assert_eq!(interp.state().i, i);
+        }
+    }
+
+    fn draw_at_right_edge(clip_sprites: bool) -> Chip8Interpreter {
+        let config = Chip8Config {
+            quirks: Quirks {
+                clip_sprites,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rom = [
+            0x60, 0x3e, 0x61, 0x00, 0xa2, 0x0a, 0xd0, 0x11, 0x12, 0x08, 0xff,
+        ];
+        let mut interp = interpreter_with_config(config, &rom);
+        interp.run_cycles(4).unwrap();
+        assert_eq!(interp.state().screen[0][62..], [1, 1]);
+        interp
+    }
+
+    #[test]
+    fn sprites_wrap_without_clipping() {
+        let interp = draw_at_right_edge(false);
+        assert_eq!(interp.state().screen[0][..7], [1, 1, 1, 1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn sprites_are_cut_off_with_clipping() {
+        let interp = draw_at_right_edge(true);
+        assert_eq!(lit_pixels(&interp), 2);
+        assert_eq!(interp.state().screen[0][..2], [0, 0]);
+    }
+
+    #[test]
+    fn sprite_start_position_wraps_with_clipping() {
+        let config = Chip8Config {
+            quirks: Quirks {
+                clip_sprites: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // Draws the 0 glyph at (66, 33), which wraps around to (2, 1)
+        let rom = [0x60, 0x42, 0x61, 0x21, 0xd0, 0x11];
+        let mut interp = interpreter_with_config(config, &rom);
+        interp.run_cycles(3).unwrap();
+        assert_eq!(interp.state().screen[1][..7], [0, 0, 1, 1, 1, 1, 0]);
+    }
+
+    fn display_wait_interpreter(rom: &[u8]) -> Chip8Interpreter {
+        let config = Chip8Config {
+            variant: Chip8Variant::SuperChip,
+            quirks: Quirks {
+                display_wait: true,
+                display_wait_lowres_only: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut interp = interpreter_with_config(config, rom);
+        // Only count the timers down by hand, so vertical blank comes when the test says
+        interp.set_tick_drives_timers(false);
+        interp
+    }
+
+    #[test]
+    fn lores_draws_wait_for_vertical_blank() {
+        let mut interp = display_wait_interpreter(&[0xd0, 0x11, 0xd0, 0x11]);
+
+        interp.tick().unwrap();
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 2);
+        interp.tick().unwrap();
+        interp.tick().unwrap();
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 2);
+
+        interp.tick_timers();
+        interp.tick().unwrap();
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 4);
+    }
+
+    #[test]
+    fn hires_draws_do_not_wait_when_scoped_to_lores() {
+        let mut interp = display_wait_interpreter(&[0x00, 0xff, 0xd0, 0x11, 0xd0, 0x11]);
+
+        interp.run_cycles(3).unwrap();
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 6);
+    }
+
+    #[test]
+    fn hires_draws_wait_without_lores_scope() {
+        let mut interp = display_wait_interpreter(&[0x00, 0xff, 0xd0, 0x11, 0xd0, 0x11]);
+        let mut quirks = interp.quirks();
+        quirks.display_wait_lowres_only = false;
+        interp.set_quirks(quirks);
+
+        interp.run_cycles(3).unwrap();
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 4);
+    }
+
+    #[test]
+    fn step_back_restores_intermediate_states() {
+        let mut interp = interpreter(
+            Chip8Variant::ChipModern,
+            &[0x60, 0x01, 0x61, 0x02, 0x62, 0x03],
+        );
+        interp.set_history_depth(8);
+
+        let mut snapshots = vec![interp.snapshot()];
+        for _ in 0..3 {
+            interp.tick().unwrap();
+            snapshots.push(interp.snapshot());
+        }
+
+        interp.step_back().unwrap();
+        assert!(interp.state() == &snapshots[2]);
+        interp.step_back().unwrap();
+        assert!(interp.state() == &snapshots[1]);
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 2);
+        assert_eq!(interp.state().registers[..3], [1, 0, 0]);
+        assert_eq!(interp.history_len(), 1);
+    }
+
+    #[test]
+    fn step_back_fails_without_history() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x60, 0x01]);
+        interp.tick().unwrap();
+        assert!(matches!(
+            interp.step_back(),
+            Err(Chip8InterpreterError::HistoryEmpty)
+        ));
+    }
+
+    #[test]
+    fn stepping_back_and_forth_replays_random_and_draws() {
+        // Draw a random glyph at a random position, over and over
+        let rom = [
+            0xc0, 0x3f, 0xc1, 0x1f, 0xc2, 0x0f, 0xf2, 0x29, 0xd0, 0x15, 0x12, 0x00,
+        ];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        interp.set_history_depth(64);
+
+        let start = interp.snapshot();
+        interp.run_cycles(30).unwrap();
+        let end = interp.snapshot();
+
+        for _ in 0..30 {
+            interp.step_back().unwrap();
+        }
+        assert!(interp.state() == &start);
+
+        interp.run_cycles(30).unwrap();
+        assert!(interp.state() == &end);
+    }
+
+    #[test]
+    fn new_rng_discards_replayed_bytes() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0xc0, 0xff]);
+        interp.set_history_depth(8);
+        interp.tick().unwrap();
+        interp.step_back().unwrap();
+
+        interp.set_rng(rand::rngs::mock::StepRng::new(0x42, 0));
+        interp.tick().unwrap();
+        assert_eq!(interp.state().registers[0], 0x42);
+    }
+
+    #[test]
+    fn step_out_returns_from_nested_call() {
+        // 0x200 calls 0x206, which calls 0x20C, which returns twice
+        let rom = [
+            0x22, 0x06, 0x60, 0x01, 0x12, 0x04, 0x22, 0x0c, 0x61, 0x02, 0x00, 0xee, 0x62, 0x03,
+            0x00, 0xee,
+        ];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        interp.run_cycles(2).unwrap();
+        assert_eq!(interp.state().pc, 0x20c);
+        assert_eq!(interp.state().sp, 2);
+
+        interp.step_out(100).unwrap();
+        assert_eq!(interp.state().pc, 0x208);
+        assert_eq!(interp.state().sp, 1);
+        assert_eq!(interp.state().registers[2], 3);
+
+        interp.step_out(100).unwrap();
+        assert_eq!(interp.state().pc, 0x202);
+        assert_eq!(interp.state().sp, 0);
+        assert_eq!(interp.state().registers[1], 2);
+    }
+
+    #[test]
+    fn step_out_needs_a_subroutine() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x12, 0x00]);
+        assert!(matches!(
+            interp.step_out(100),
+            Err(Chip8InterpreterError::NotInSubroutine { pc: BASE_ADDRESS })
+        ));
+    }
+
+    #[test]
+    fn step_out_gives_up_after_max_ticks() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x22, 0x02, 0x12, 0x02]);
+        interp.tick().unwrap();
+        assert!(matches!(
+            interp.step_out(10),
+            Err(Chip8InterpreterError::ExecutionLimitReached { cycles: 10 })
+        ));
+    }
+
+    #[test]
+    fn breakpoint_halts_before_its_instruction_and_resumes() {
+        let mut interp = interpreter(
+            Chip8Variant::ChipModern,
+            &[0x60, 0x01, 0x61, 0x02, 0x62, 0x03],
+        );
+        interp.add_breakpoint(0x204);
+
+        interp.run_cycles(2).unwrap();
+        assert!(matches!(
+            interp.tick(),
+            Err(Chip8InterpreterError::BreakpointHit(0x204))
+        ));
+        assert_eq!(interp.state().pc, 0x204);
+        assert_eq!(interp.state().registers[2], 0);
+        assert_eq!(interp.events(), [Chip8Event::BreakpointHit(0x204)]);
+
+        interp.tick().unwrap();
+        assert_eq!(interp.state().pc, 0x206);
+        assert_eq!(interp.state().registers[2], 3);
+    }
+
+    #[test]
+    fn breakpoint_hits_again_on_the_next_pass() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x70, 0x01, 0x12, 0x00]);
+        interp.add_breakpoint(0x200);
+
+        for pass in 1..=3 {
+            assert!(interp.tick().is_err());
+            interp.run_cycles(2).unwrap();
+            assert_eq!(interp.state().registers[0], pass);
+        }
+
+        assert!(interp.remove_breakpoint(0x200));
+        interp.run_cycles(2).unwrap();
+        assert_eq!(interp.state().registers[0], 4);
+    }
+
+    #[test]
+    fn watchpoint_fires_after_the_write() {
+        let mut interp = interpreter(
+            Chip8Variant::ChipModern,
+            &[0x60, 0xfe, 0xa3, 0x00, 0xf0, 0x33],
+        );
+        interp.add_watchpoint(0x301);
+
+        interp.run_cycles(2).unwrap();
+        assert!(matches!(
+            interp.tick(),
+            Err(Chip8InterpreterError::WatchpointHit {
+                address: 0x301,
+                value: 5
+            })
+        ));
+        assert_eq!(interp.state().memory[0x300..0x303], [2, 5, 4]);
+        assert_eq!(interp.state().pc, 0x206);
+    }
+
+    #[test]
+    fn unwatched_writes_do_not_stop() {
+        let mut interp = interpreter(
+            Chip8Variant::ChipModern,
+            &[0x60, 0xfe, 0xa3, 0x00, 0xf0, 0x33],
+        );
+        interp.add_watchpoint(0x303);
+
+        interp.run_cycles(3).unwrap();
+        assert!(interp.remove_watchpoint(0x303));
+        assert!(interp.watchpoints().is_empty());
+    }
+
+    #[test]
+    fn long_index_loads_following_word() {
+        let mut interp = interpreter(Chip8Variant::XoChip, &[0xf0, 0x00, 0x0a, 0xbc, 0x60, 0x01]);
+
+        assert!(matches!(
+            interp.step(),
+            Ok(Chip8Instruction::LoadLongIndex { address: 0x0abc })
+        ));
+        assert_eq!(interp.state().i, 0x0abc);
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 4);
+
+        interp.tick().unwrap();
+        assert_eq!(interp.state().registers[0], 1);
+    }
+
+    #[test]
+    fn skips_step_over_long_index_as_a_whole() {
+        let rom = [0x30, 0x00, 0xf0, 0x00, 0x0a, 0xbc, 0x60, 0x01];
+        let mut interp = interpreter(Chip8Variant::XoChip, &rom);
+
+        interp.tick().unwrap();
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 6);
+        interp.tick().unwrap();
+        assert_eq!(interp.state().i, 0);
+        assert_eq!(interp.state().registers[0], 1);
+    }
+
+    #[test]
+    fn long_index_is_xo_chip_only() {
+        let mut interp = interpreter(Chip8Variant::SuperChip, &[0xf0, 0x00, 0x0a, 0xbc]);
+        assert!(matches!(
+            interp.tick(),
+            Err(Chip8InterpreterError::InvalidInstruction(0xf000))
+        ));
+    }
+
+    #[test]
+    fn draw_and_clear_screen_set_screen_dirty() {
+        let mut interp = interpreter(
+            Chip8Variant::ChipModern,
+            &[0x60, 0x01, 0xd0, 0x05, 0x00, 0xe0],
+        );
+        interp.clear_screen_dirty();
+
+        interp.tick().unwrap();
+        assert!(!interp.screen_dirty());
+
+        interp.tick().unwrap();
+        assert!(interp.screen_dirty());
+        interp.clear_screen_dirty();
+
+        interp.tick().unwrap();
+        assert!(interp.screen_dirty());
+    }
+
+    #[test]
+    fn disassemble_range_decodes_known_opcodes() {
+        let rom = [0x00, 0xe0, 0x6a, 0x2b, 0xa1, 0x23, 0xd0, 0x15, 0xf0, 0xff];
+        let interp = interpreter(Chip8Variant::ChipModern, &rom);
+
+        let listing = interp.disassemble_range(0x200, 0x20a);
+        let addresses: Vec<_> = listing.iter().map(|decoded| decoded.address).collect();
+        let opcodes: Vec<_> = listing.iter().map(|decoded| decoded.opcode).collect();
+        assert_eq!(addresses, [0x200, 0x202, 0x204, 0x206, 0x208]);
+        assert_eq!(opcodes, [0x00e0, 0x6a2b, 0xa123, 0xd015, 0xf0ff]);
+
+        assert!(matches!(
+            listing[0].instruction,
+            Ok(Chip8Instruction::ClearScreen)
+        ));
+        assert!(matches!(
+            listing[1].instruction,
+            Ok(Chip8Instruction::LoadValue {
+                register: 0xa,
+                value: 0x2b
+            })
+        ));
+        assert!(matches!(
+            listing[2].instruction,
+            Ok(Chip8Instruction::SetIndex { address: 0x123 })
+        ));
+        assert!(matches!(
+            listing[3].instruction,
+            Ok(Chip8Instruction::Draw { x: 0, y: 1, len: 5 })
+        ));
+        assert!(listing[4].instruction.is_err());
+    }
+
+    #[test]
+    fn disassemble_range_decodes_from_odd_starts() {
+        let rom = [0x00, 0xe0, 0x6a, 0x2b, 0xa1, 0x23];
+        let interp = interpreter(Chip8Variant::ChipModern, &rom);
+
+        let listing = interp.disassemble_range(0x201, 0x205);
+        let opcodes: Vec<_> = listing
+            .iter()
+            .map(|decoded| (decoded.address, decoded.opcode))
+            .collect();
+        assert_eq!(opcodes, [(0x201, 0xe06a), (0x203, 0x2ba1)]);
+    }
+
+    #[test]
+    fn disassembly_json_lists_every_instruction_with_labels() {
+        let interp = interpreter(
+            Chip8Variant::ChipModern,
+            &[0x00, 0xe0, 0xf0, 0xff, 0x12, 0x00],
+        );
+        let mut json = vec![];
+        interp
+            .write_disassembly_json(&mut json, |address| {
+                (address == 0x204).then(|| "main loop".to_string())
+            })
+            .unwrap();
+
+        let entries: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let entries = entries.as_array().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0]["address"], 0x200);
+        assert_eq!(entries[0]["opcode"], 0x00e0);
+        assert_eq!(entries[0]["valid"], true);
+        assert_eq!(entries[0]["category"], "Chip8");
+        assert!(entries[0].get("label").is_none());
+        assert_eq!(entries[1]["valid"], false);
+        assert_eq!(entries[1]["mnemonic"], "");
+        assert_eq!(entries[2]["label"], "main loop");
+    }
+
+    #[test]
+    fn disassembly_lists_header_and_instructions() {
+        let interp = interpreter(
+            Chip8Variant::SuperChip,
+            &[0x00, 0xe0, 0xf0, 0xff, 0x12, 0x00],
+        );
+        let mut listing = vec![];
+        interp.write_disassembly(&mut listing).unwrap();
+        let listing = String::from_utf8(listing).unwrap();
+
+        let lines: Vec<_> = listing.lines().collect();
+        assert_eq!(lines[0], format!("; ROM hash: {:016x}", interp.rom_hash()));
+        assert_eq!(lines[1], "; Variant: SUPER-CHIP");
+        assert_eq!(lines[2], "; Size: 6 bytes");
+        assert_eq!(lines[3], "");
+        assert_eq!(lines[4], "0200:  00e0  ClearScreen");
+        assert_eq!(lines[5], "0202:  f0ff  ; data");
+        assert!(lines[6].starts_with("0204:  1200  "));
+        assert_eq!(lines.len(), 7);
+    }
+
+    #[test]
+    fn headless_run_stops_at_self_jump() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x60, 0x01, 0x12, 0x02]);
+        interp
+            .run_headless(1000, None, HeadlessKeyPolicy::Stop)
+            .unwrap();
+        assert_eq!(interp.state().pc, 0x202);
+        assert!(!interp.is_halted());
+    }
+
+    #[test]
+    fn headless_run_stops_at_exit() {
+        let mut interp = interpreter(Chip8Variant::SuperChip, &[0x60, 0x01, 0x00, 0xfd]);
+        interp
+            .run_headless(1000, None, HeadlessKeyPolicy::Stop)
+            .unwrap();
+        assert!(interp.is_halted());
+    }
+
+    #[test]
+    fn headless_run_hits_cycle_limit_in_tight_loop() {
+        // Two instructions jumping to each other never park on a single self-jump
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x12, 0x02, 0x12, 0x00]);
+        assert!(matches!(
+            interp.run_headless(1000, None, HeadlessKeyPolicy::Stop),
+            Err(Chip8InterpreterError::ExecutionLimitReached { cycles: 1000 })
+        ));
+    }
+
+    #[test]
+    fn headless_run_hits_timeout() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x12, 0x02, 0x12, 0x00]);
+        assert!(matches!(
+            interp.run_headless(u64::MAX, Some(Duration::ZERO), HeadlessKeyPolicy::Stop),
+            Err(Chip8InterpreterError::ExecutionLimitReached {
+                cycles: WATCHDOG_CHECK_INTERVAL
+            })
+        ));
+    }
+
+    #[test]
+    fn loaded_state_keeps_key_released_during_wait() {
+        let config = Chip8Config {
+            wait_for_key_release: true,
+            ..Default::default()
+        };
+        let mut interp = interpreter_with_config(config, &[0xf0, 0x0a, 0x12, 0x02]);
+        interp.press_key(5).unwrap();
+        interp.tick().unwrap();
+        interp.release_key(5).unwrap();
+        assert_eq!(interp.state().pc, BASE_ADDRESS);
+
+        let mut loaded = interpreter_with_config(config, &[]);
+        loaded.load_state(interp.save_state());
+        loaded.tick().unwrap();
+        assert_eq!(loaded.state().pc, BASE_ADDRESS + 2);
+        assert_eq!(loaded.state().registers[0], 5);
+    }
+
+    #[test]
+    fn loaded_state_resumes_like_an_uninterrupted_run() {
+        // Count the delay timer down from 0x20 while waiting for a key
+        let rom = [0x60, 0x20, 0xf0, 0x15, 0xf1, 0x0a, 0xf2, 0x07, 0x12, 0x04];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        interp.schedule_input(40, 1 << 3);
+        interp.schedule_input(45, 0);
+        // Stalled on WaitForKey, partway between two timer ticks
+        interp.run_cycles(25).unwrap();
+        assert!(interp.is_waiting_for_key());
+        assert_ne!(interp.instructions_until_timer_tick(), Some(0));
+
+        let mut loaded = Chip8Interpreter::new();
+        loaded.load_state(interp.save_state());
+        interp.run_cycles(100).unwrap();
+        loaded.run_cycles(100).unwrap();
+
+        assert!(loaded.state() == interp.state());
+        assert_eq!(loaded.ticks(), interp.ticks());
+        assert_eq!(
+            loaded.instructions_until_timer_tick(),
+            interp.instructions_until_timer_tick()
+        );
+        assert_eq!(interp.state().registers[1], 3);
+    }
+
+    #[test]
+    fn ticks_count_stalls_so_input_reaches_a_waiting_program() {
+        let rom = [0xf1, 0x0a, 0x12, 0x02];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        interp.schedule_input(10, 1 << 7);
+        interp.schedule_input(11, 0);
+
+        interp.run_cycles(10).unwrap();
+        assert!(interp.is_waiting_for_key());
+        assert_eq!(interp.ticks(), 10);
+        assert_eq!(interp.state().pc, BASE_ADDRESS);
+
+        interp.run_cycles(5).unwrap();
+        assert_eq!(interp.ticks(), 15);
+        assert_eq!(interp.state().registers[1], 7);
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 2);
+    }
+
+    #[test]
+    fn timer_events_are_kept_until_drained() {
+        // Set the sound timer to 1, then spin
+        let rom = [0x60, 0x01, 0xf0, 0x18, 0x12, 0x04];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        interp.run_cycles(2).unwrap();
+        interp.tick_timers();
+        interp.tick().unwrap();
+
+        assert_eq!(
+            interp.take_events(),
+            [Chip8Event::SoundStarted, Chip8Event::SoundStopped]
+        );
+        assert!(interp.events().is_empty());
+    }
+
+    #[test]
+    fn bcd_at_top_of_long_index_range_is_rejected() {
+        let rom = [0xf0, 0x00, 0xff, 0xff, 0xf0, 0x33];
+        let mut interp = interpreter(Chip8Variant::XoChip, &rom);
+
+        interp.tick().unwrap();
+        assert_eq!(interp.state().i, 0xffff);
+        assert!(matches!(
+            interp.tick(),
+            Err(Chip8InterpreterError::MemoryAccessError)
+        ));
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 4);
+    }
+
+    #[test]
+    fn set_pc_rejects_odd_and_out_of_bounds_addresses() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x12, 0x00]);
+
+        assert!(matches!(
+            interp.set_pc(0x301, false),
+            Err(Chip8InterpreterError::UnalignedProgramCounter(0x301))
+        ));
+        assert!(matches!(
+            interp.set_pc(0xfff, false),
+            Err(Chip8InterpreterError::ProgramCounterOutOfBounds(0xfff))
+        ));
+        assert_eq!(interp.state().pc, BASE_ADDRESS);
+
+        interp.set_pc(0xffe, false).unwrap();
+        assert_eq!(interp.state().pc, 0xffe);
+    }
+
+    #[test]
+    fn set_pc_can_clear_the_stack() {
+        // Call a subroutine that spins
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x22, 0x02, 0x12, 0x02]);
+        interp.tick().unwrap();
+        assert_eq!(interp.state().sp, 1);
+
+        interp.set_pc(BASE_ADDRESS, false).unwrap();
+        assert_eq!(interp.state().sp, 1);
+        assert_eq!(interp.state().stack[0], BASE_ADDRESS + 2);
+
+        interp.set_pc(BASE_ADDRESS, true).unwrap();
+        assert_eq!(interp.state().pc, BASE_ADDRESS);
+        assert_eq!(interp.state().sp, 0);
+        assert_eq!(interp.state().stack, [0; STACK_SIZE]);
+    }
+
+    #[test]
+    fn cycles_count_instruction_costs_but_not_stalls() {
+        let config = Chip8Config {
+            cycle_costs: CycleCosts {
+                base: 1,
+                per_sprite_row: 2,
+                per_register: 0,
+            },
+            ..Default::default()
+        };
+        // Draw a five row sprite, then wait for a key
+        let rom = [0xa0, 0x00, 0xd0, 0x05, 0xf0, 0x0a];
+        let mut interp = interpreter_with_config(config, &rom);
+
+        interp.run_cycles(2).unwrap();
+        assert_eq!(interp.cycles(), 12);
+        interp.run_cycles(10).unwrap();
+        assert!(interp.is_waiting_for_key());
+        assert_eq!(interp.ticks(), 12);
+        assert_eq!(interp.cycles(), 12);
+    }
+
+    #[test]
+    fn buzzer_sounds_while_the_sound_timer_is_non_zero() {
+        // Set the sound timer to 2, then spin
+        let rom = [0x60, 0x02, 0xf0, 0x18, 0x12, 0x04];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        assert!(!interp.should_buzz());
+        interp.run_cycles(2).unwrap();
+        assert_eq!(interp.state().st, 2);
+        assert!(interp.should_buzz());
+
+        interp.tick_timers();
+        assert_eq!(interp.state().st, 1);
+        assert!(interp.should_buzz());
+
+        interp.tick_timers();
+        assert_eq!(interp.state().st, 0);
+        assert!(!interp.should_buzz());
+    }
+
+    #[test]
+    fn exit_halts_the_interpreter() {
+        let mut interp = interpreter(Chip8Variant::SuperChip, &[0x00, 0xfd]);
+        assert!(matches!(interp.step(), Ok(Chip8Instruction::Exit)));
+        assert!(interp.is_halted());
+        assert_eq!(interp.state().pc, BASE_ADDRESS + 2);
+
+        // Further ticks keep reporting the exit without running anything
+        assert!(matches!(interp.step(), Ok(Chip8Instruction::Exit)));
+        assert_eq!(interp.ticks(), 1);
+    }
+
+    /// ROM drawing a four pixel wide line at (8, 4), switching to hires first if asked, followed
+    /// by `scroll`
+    fn scroll_rom(hires: bool, scroll: [u8; 2]) -> Vec<u8> {
+        let mut rom = vec![0x00, if hires { 0xff } else { 0xfe }];
+        rom.extend([0xa2, 0x0c, 0x60, 0x08, 0x61, 0x04, 0xd0, 0x11]);
+        rom.extend(scroll);
+        rom.extend([0xf0, 0x00]);
+        rom
+    }
+
+    fn lit_row(interp: &Chip8Interpreter, row: usize) -> Vec<usize> {
+        let screen = &interp.state().screen;
+        (0..SCREEN_WIDTH).filter(|&x| screen[row][x] != 0).collect()
+    }
+
+    #[test]
+    fn scrolls_move_the_screen_by_half_the_amount() {
+        for hires in [false, true] {
+            let mut interp = interpreter(Chip8Variant::SuperChip, &scroll_rom(hires, [0x00, 0xc4]));
+            interp.run_cycles(6).unwrap();
+            assert!(lit_row(&interp, 4).is_empty());
+            assert_eq!(lit_row(&interp, 6), [8, 9, 10, 11]);
+            assert_eq!(lit_pixels(&interp), 4);
+
+            let mut interp = interpreter(Chip8Variant::SuperChip, &scroll_rom(hires, [0x00, 0xfb]));
+            interp.run_cycles(6).unwrap();
+            assert_eq!(lit_row(&interp, 4), [10, 11, 12, 13]);
+
+            let mut interp = interpreter(Chip8Variant::SuperChip, &scroll_rom(hires, [0x00, 0xfc]));
+            interp.run_cycles(6).unwrap();
+            assert_eq!(lit_row(&interp, 4), [6, 7, 8, 9]);
+        }
+    }
+
+    #[test]
+    fn scrolls_drop_what_leaves_the_screen() {
+        // Scroll a line at the bottom down, and one at the left edge left
+        let mut rom = vec![0xa2, 0x0e, 0x61, 0x1f, 0xd0, 0x11, 0x00, 0xc4];
+        rom.extend([0x61, 0x00, 0xd0, 0x11, 0x00, 0xfc, 0xc0, 0x00]);
+        let mut interp = interpreter(Chip8Variant::SuperChip, &rom);
+        interp.run_cycles(3).unwrap();
+        assert_eq!(lit_row(&interp, 31), [0, 1]);
+        interp.tick().unwrap();
+        assert_eq!(lit_pixels(&interp), 0);
+
+        interp.run_cycles(2).unwrap();
+        assert_eq!(lit_row(&interp, 0), [0, 1]);
+        interp.tick().unwrap();
+        assert_eq!(lit_pixels(&interp), 0);
+    }
+
+    /// Interpreter for `rom` with every register holding `0x10` plus its number
+    fn numbered_registers(rom: &[u8]) -> Chip8Interpreter {
+        let mut interp = interpreter(Chip8Variant::XoChip, rom);
+        for register in 0..REGISTER_COUNT {
+            interp
+                .set_register(register, 0x10 + register as u8)
+                .unwrap();
+        }
+        interp
+    }
+
+    #[test]
+    fn register_range_stores_in_the_given_order() {
+        let stored = |range: [u8; 2]| {
+            let mut interp = numbered_registers(&[0xa3, 0x00, range[0], range[1]]);
+            interp.run_cycles(2).unwrap();
+            assert_eq!(interp.state().i, 0x300);
+            interp.state().memory[0x300..0x305].to_vec()
+        };
+
+        assert_eq!(stored([0x52, 0x52]), [0x12, 0x13, 0x14, 0x15, 0x00]);
+        assert_eq!(stored([0x55, 0x22]), [0x15, 0x14, 0x13, 0x12, 0x00]);
+        assert_eq!(stored([0x57, 0x72]), [0x17, 0x00, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn register_range_loads_in_the_given_order() {
+        // Point I at the bytes 01 02 03 04 following the instructions
+        let loaded = |range: [u8; 2]| {
+            let rom = [0xa2, 0x04, range[0], range[1], 0x01, 0x02, 0x03, 0x04];
+            let mut interp = numbered_registers(&rom);
+            interp.run_cycles(2).unwrap();
+            assert_eq!(interp.state().i, 0x204);
+            interp.state().registers[2..8].to_vec()
+        };
+
+        assert_eq!(loaded([0x53, 0x63]), [0x12, 0x01, 0x02, 0x03, 0x04, 0x17]);
+        assert_eq!(loaded([0x56, 0x33]), [0x12, 0x04, 0x03, 0x02, 0x01, 0x17]);
+        assert_eq!(loaded([0x55, 0x53]), [0x12, 0x13, 0x14, 0x01, 0x16, 0x17]);
+    }
+
+    #[test]
+    fn flags_store_and_restore_registers() {
+        let mut interp = numbered_registers(&[0xf3, 0x75, 0xf3, 0x85]);
+        interp.tick().unwrap();
+        assert_eq!(interp.rpl_flags(), &[0x10, 0x11, 0x12, 0x13, 0, 0, 0, 0]);
+
+        for register in 0..REGISTER_COUNT {
+            interp.set_register(register, 0).unwrap();
+        }
+        interp.tick().unwrap();
+        assert_eq!(interp.state().registers[..5], [0x10, 0x11, 0x12, 0x13, 0]);
+    }
+
+    #[test]
+    fn flags_hold_at_most_eight_registers() {
+        let mut interp = numbered_registers(&[0xff, 0x75, 0xff, 0x85]);
+        interp.tick().unwrap();
+        assert_eq!(
+            interp.rpl_flags(),
+            &[0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17]
+        );
+
+        for register in 0..REGISTER_COUNT {
+            interp.set_register(register, 0).unwrap();
+        }
+        interp.tick().unwrap();
+        assert_eq!(
+            interp.state().registers[..RPL_FLAG_COUNT],
+            [0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17]
+        );
+        assert_eq!(interp.state().registers[RPL_FLAG_COUNT..], [0; 8]);
+    }
+
+    #[test]
+    fn draws_to_the_second_plane_leave_the_first_untouched() {
+        // Draw a line on plane 1 at x 8, then one on plane 2 overlapping it at x 10
+        let rom = [
+            0xa2, 0x0c, 0x60, 0x08, 0xd0, 0x11, 0xf2, 0x01, 0x60, 0x0a, 0xd0, 0x11, 0xf0, 0x00,
+        ];
+        let mut interp = interpreter(Chip8Variant::XoChip, &rom);
+        interp.run_cycles(3).unwrap();
+        let first_plane = interp.state().screen;
+        interp.run_cycles(3).unwrap();
+
+        let screen = &interp.state().screen;
+        assert_eq!(screen[0][8..14], [1, 1, 3, 3, 2, 2]);
+        for (row, first_row) in screen.iter().zip(&first_plane) {
+            for (pixel, first_pixel) in row.iter().zip(first_row) {
+                assert_eq!(pixel & 1, *first_pixel);
+            }
+        }
+        // Lit pixels on the first plane don't collide with the second
+        assert_eq!(interp.state().registers[0xf], 0);
+    }
+
+    #[test]
+    fn store_and_load_registers_count_x_inclusively() {
+        // F055 and F065 touch V0 only
+        let mut interp = numbered_registers(&[0xa3, 0x00, 0xf0, 0x55, 0xa2, 0x00, 0xf0, 0x65]);
+        interp.run_cycles(2).unwrap();
+        assert_eq!(interp.state().memory[0x300..0x302], [0x10, 0x00]);
+        interp.run_cycles(2).unwrap();
+        assert_eq!(interp.state().registers[..2], [0xa3, 0x11]);
+
+        // FF55 and FF65 touch all sixteen, which fit exactly at the top of memory
+        let mut interp = numbered_registers(&[0xaf, 0xf0, 0xff, 0x55, 0xff, 0x65]);
+        interp.run_cycles(2).unwrap();
+        let expected: Vec<u8> = (0x10..0x20).collect();
+        assert_eq!(interp.state().memory[0xff0..], expected);
+        for register in 0..REGISTER_COUNT {
+            interp.set_register(register, 0).unwrap();
+        }
+        interp.tick().unwrap();
+        assert_eq!(interp.state().registers.to_vec(), expected);
+    }
+
+    #[test]
+    fn sixteen_registers_past_the_top_of_memory_are_rejected() {
+        for opcode in [0x55, 0x65] {
+            let mut interp = numbered_registers(&[0xaf, 0xf1, 0xff, opcode]);
+            interp.tick().unwrap();
+            assert!(matches!(
+                interp.tick(),
+                Err(Chip8InterpreterError::MemoryAccessError)
+            ));
+            assert_eq!(interp.state().memory[0xff1..], [0; 15]);
+            assert_eq!(interp.state().registers[0], 0x10);
+        }
+    }
+
+    #[test]
+    fn failed_instructions_leave_pc_on_themselves() {
+        // FF55 past the end of memory, and a relative jump out of memory
+        for rom in [[0xaf, 0xf8, 0xff, 0x55], [0x60, 0x10, 0xbf, 0xff]] {
+            let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+            interp.tick().unwrap();
+            for _ in 0..2 {
+                assert!(matches!(
+                    interp.tick(),
+                    Err(Chip8InterpreterError::MemoryAccessError)
+                ));
+                assert_eq!(interp.state().pc, BASE_ADDRESS + 2);
+            }
+        }
+    }
+
+    #[test]
+    fn stack_overflow_reports_the_call_and_depth() {
+        // Recurse without ever returning
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x60, 0x00, 0x22, 0x02]);
+        interp.run_cycles(STACK_SIZE + 1).unwrap();
+        assert!(matches!(
+            interp.tick(),
+            Err(Chip8InterpreterError::CallStackDepthExceeded {
+                pc: 0x202,
+                depth: STACK_SIZE
+            })
+        ));
+    }
+
+    #[test]
+    fn stack_underflow_reports_the_return() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x60, 0x00, 0x00, 0xee]);
+        interp.tick().unwrap();
+        assert!(matches!(
+            interp.tick(),
+            Err(Chip8InterpreterError::CallStackEmpty { pc: 0x202 })
+        ));
+    }
+
+    #[test]
+    fn stack_underflow_emits_an_event_and_stays_on_the_return() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x60, 0x00, 0x00, 0xee]);
+        interp.tick().unwrap();
+        assert!(interp.tick().is_err());
+        assert_eq!(interp.take_events(), [Chip8Event::StackUnderflow(0x202)]);
+        assert_eq!(interp.state().pc, 0x202);
+        assert_eq!(interp.state().sp, 0);
+    }
+
+    /// Values `V0` takes on over a loop of `C0FF`
+    fn random_stream(interp: &mut Chip8Interpreter) -> Vec<u8> {
+        interp.try_load_rom(&[0xc0, 0xff, 0x12, 0x00]).unwrap();
+        (0..32)
+            .map(|_| {
+                interp.run_cycles(2).unwrap();
+                interp.state().registers[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identically_seeded_interpreters_draw_the_same_random_bytes() {
+        let stream = random_stream(&mut Chip8Interpreter::with_seed(42));
+        assert_eq!(random_stream(&mut Chip8Interpreter::with_seed(42)), stream);
+        assert_ne!(random_stream(&mut Chip8Interpreter::with_seed(43)), stream);
+    }
+
+    #[test]
+    fn reseeding_on_reset_repeats_the_random_bytes() {
+        for reseed_on_reset in [true, false] {
+            let mut interp = Chip8Interpreter::with_seed(7);
+            interp.set_config(Chip8Config {
+                reseed_on_reset,
+                ..*interp.config()
+            });
+            let first = random_stream(&mut interp);
+            interp.reset();
+            let second = random_stream(&mut interp);
+            assert_eq!(first == second, reseed_on_reset);
+        }
+    }
+
+    #[test]
+    fn instructions_cover_the_loaded_rom() {
+        let interp = interpreter(
+            Chip8Variant::ChipModern,
+            &[0x00, 0xe0, 0x6a, 0x2b, 0xf0, 0xff, 0x12, 0x06],
+        );
+        let program: Vec<_> = interp.instructions().collect();
+        let addresses: Vec<_> = program.iter().map(|decoded| decoded.address).collect();
+        assert_eq!(addresses, [0x200, 0x202, 0x204, 0x206]);
+        assert!(matches!(
+            program[0].instruction,
+            Ok(Chip8Instruction::ClearScreen)
+        ));
+        assert!(matches!(
+            program[1].instruction,
+            Ok(Chip8Instruction::LoadValue {
+                register: 0xa,
+                value: 0x2b
+            })
+        ));
+        assert!(program[2].instruction.is_err());
+        assert!(matches!(
+            program[3].instruction,
+            Ok(Chip8Instruction::Jump { address: 0x206 })
+        ));
+    }
+
+    #[test]
+    fn instructions_without_a_rom_run_to_the_end_of_memory() {
+        let interp = Chip8Interpreter::new();
+        let last = interp.instructions().last().unwrap();
+        assert_eq!(interp.instructions().count(), (0x1000 - 0x200) / 2);
+        assert_eq!(last.address, 0xffe);
+    }
+
+    #[test]
+    fn decoded_instructions_keep_address_and_opcode() {
+        // A scroll, which only decodes for SUPER-CHIP, and a jump
+        let rom = [0x00, 0xc4, 0x12, 0x02];
+        for (variant, scroll_decodes) in [
+            (Chip8Variant::ChipModern, false),
+            (Chip8Variant::SuperChip, true),
+        ] {
+            let interp = interpreter(variant, &rom);
+            let scroll = interp.decode_at(0x200);
+            assert_eq!((scroll.address, scroll.opcode), (0x200, 0x00c4));
+            match scroll.instruction {
+                Ok(Chip8Instruction::ScrollDown { amount: 4 }) => assert!(scroll_decodes),
+                Err(Chip8InterpreterError::InvalidInstruction(0x00c4)) => assert!(!scroll_decodes),
+                other => panic!("00c4 decoded to {:?}", other),
+            }
+
+            let jump = interp.decode_at(0x202);
+            assert_eq!((jump.address, jump.opcode), (0x202, 0x1202));
+            assert!(matches!(
+                jump.instruction,
+                Ok(Chip8Instruction::Jump { address: 0x202 })
+            ));
+        }
+
+        // The last byte of memory has no second byte to decode
+        let end = Chip8Interpreter::new().decode_at(0xfff);
+        assert_eq!((end.address, end.opcode), (0xfff, 0x0000));
+        assert!(matches!(
+            end.instruction,
+            Err(Chip8InterpreterError::MemoryAccessError)
+        ));
+    }
+
+    #[test]
+    fn short_run_emits_events_in_order() {
+        // Clear, draw a pixel twice, start the buzzer, then hit data
+        let rom = [
+            0x00, 0xe0, 0xa2, 0x0e, 0xd0, 0x11, 0xd0, 0x11, 0x60, 0x05, 0xf0, 0x18, 0xf0, 0xff,
+            0x80,
+        ];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        interp.run_cycles(6).unwrap();
+        assert!(interp.tick().is_err());
+
+        assert_eq!(
+            interp.take_events(),
+            [
+                Chip8Event::ScreenCleared,
+                Chip8Event::Collision,
+                Chip8Event::SoundStarted,
+                Chip8Event::InvalidOpcode {
+                    address: 0x20c,
+                    opcode: 0xf0ff
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn headless_run_supplies_keys_to_wait_for_key() {
+        // Wait for two keys, then spin
+        let rom = [0xf1, 0x0a, 0xf2, 0x0a, 0x12, 0x04];
+        for wait_for_key_release in [false, true] {
+            let config = Chip8Config {
+                wait_for_key_release,
+                ..Default::default()
+            };
+            let mut interp = interpreter_with_config(config, &rom);
+            interp
+                .run_headless(1000, None, HeadlessKeyPolicy::Press(7))
+                .unwrap();
+            assert_eq!(interp.state().pc, 0x204);
+            assert_eq!(interp.state().registers[1..3], [7, 7]);
+        }
+    }
+
+    #[test]
+    fn headless_run_can_stop_at_wait_for_key() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0x60, 0x01, 0xf1, 0x0a]);
+        assert!(matches!(
+            interp.run_headless(1000, None, HeadlessKeyPolicy::Stop),
+            Err(Chip8InterpreterError::WaitingForKey { pc: 0x202 })
+        ));
+        assert!(matches!(
+            interp.run_headless(1000, None, HeadlessKeyPolicy::Press(0x10)),
+            Err(Chip8InterpreterError::InvalidInputKey(0x10))
+        ));
+    }
+
+    #[test]
+    fn frozen_timers_hold_their_values() {
+        // Set both timers to 0x30, then spin
+        let rom = [0x60, 0x30, 0xf0, 0x15, 0xf0, 0x18, 0x12, 0x06];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        interp.run_cycles(3).unwrap();
+        interp.freeze_timers(true);
+
+        interp.run_cycles(5000).unwrap();
+        interp.tick_timers();
+        assert_eq!((interp.state().dt, interp.state().st), (0x30, 0x30));
+
+        interp.freeze_timers(false);
+        interp.tick_timers();
+        assert_eq!((interp.state().dt, interp.state().st), (0x2f, 0x2f));
+    }
+
+    #[test]
+    fn frozen_timers_still_let_draws_wait_for_vertical_blank() {
+        let config = Chip8Config {
+            quirks: Quirks {
+                display_wait: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut interp = interpreter_with_config(config, &[0xd0, 0x11, 0xd0, 0x11, 0x12, 0x04]);
+        interp.freeze_timers(true);
+        interp.run_cycles(1).unwrap();
+        assert_eq!(interp.state().pc, 0x202);
+
+        // The second draw stalls until the next timer tick
+        interp.run_cycles(interp.timer_tick_interval() - 2).unwrap();
+        assert_eq!(interp.state().pc, 0x202);
+        interp.run_cycles(2).unwrap();
+        assert_eq!(interp.state().pc, 0x204);
+    }
+
+    #[test]
+    fn wait_for_key_takes_the_lowest_of_several_held_keys() {
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0xf1, 0x0a, 0x12, 0x02]);
+        interp.set_input_keys(1 << 9 | 1 << 4 | 1 << 0xc);
+        interp.tick().unwrap();
+        assert_eq!(interp.state().registers[1], 4);
+        assert_eq!(interp.state().pc, 0x202);
+    }
+
+    #[test]
+    fn wait_for_key_release_takes_the_lowest_released_key() {
+        let config = Chip8Config {
+            wait_for_key_release: true,
+            ..Default::default()
+        };
+        let mut interp = interpreter_with_config(config, &[0xf1, 0x0a, 0x12, 0x02]);
+        interp.set_input_keys(1 << 2 | 1 << 5 | 1 << 9);
+        interp.tick().unwrap();
+        assert_eq!(interp.state().pc, 0x200);
+
+        // Key 2 is still held, so the lowest released key is 5
+        interp.set_input_keys(1 << 2);
+        interp.tick().unwrap();
+        assert_eq!(interp.state().registers[1], 5);
+        assert_eq!(interp.state().pc, 0x202);
+    }
+
+    #[test]
+    fn breaking_on_draw_stops_right_after_the_draw() {
+        // Two draws waiting for vertical blank, with an instruction between them
+        let config = Chip8Config {
+            quirks: Quirks {
+                display_wait: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rom = [
+            0xa2, 0x0a, 0xd0, 0x11, 0x60, 0x08, 0xd0, 0x11, 0x12, 0x08, 0x80,
+        ];
+        let mut interp = interpreter_with_config(config, &rom);
+
+        // Stop the way the app's break on draw does
+        let run_to_draw = |interp: &mut Chip8Interpreter| {
+            for _ in 0..1000 {
+                interp.tick().unwrap();
+                if let Some(Chip8Instruction::Draw { .. }) = interp.last_instruction() {
+                    return;
+                }
+            }
+            panic!("no draw executed");
+        };
+
+        run_to_draw(&mut interp);
+        assert_eq!(interp.state().pc, 0x204);
+        assert_eq!(lit_pixels(&interp), 1);
+
+        // Ticks stalled on the second draw don't count as drawing
+        run_to_draw(&mut interp);
+        assert_eq!(interp.state().pc, 0x208);
+        assert_eq!(interp.state().screen[0][8], 1);
+        assert!(interp.ticks() > 4);
+    }
+
+    #[test]
+    fn font_audit_notices_overwritten_sprites() {
+        // Store V0 over the first byte of the font
+        let mut interp = interpreter(Chip8Variant::ChipModern, &[0xa0, 0x00, 0xf0, 0x55]);
+        assert!(interp.verify_font_intact());
+        interp.run_cycles(2).unwrap();
+        assert!(!interp.verify_font_intact());
+
+        interp.reset();
+        assert!(interp.verify_font_intact());
+        interp
+            .write_memory(FONT_ROM.len() as u16 - 1, 0x00)
+            .unwrap();
+        assert!(!interp.verify_font_intact());
+    }
+
+    #[test]
+    fn cleared_screen_snapshot_holds_the_contents_before_the_clear() {
+        // Draw the 0 sprite, then clear
+        let rom = [0xd0, 0x15, 0x00, 0xe0];
+        for capture in [false, true] {
+            let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+            interp.set_capture_cleared_screen(capture);
+            interp.tick().unwrap();
+            let drawn = interp.state().screen;
+            interp.tick().unwrap();
+
+            assert_eq!(lit_pixels(&interp), 0);
+            if capture {
+                assert_eq!(interp.last_cleared_screen(), Some(&drawn));
+                assert_eq!(
+                    drawn.iter().flatten().filter(|&&pixel| pixel != 0).count(),
+                    14
+                );
+            } else {
+                assert_eq!(interp.last_cleared_screen(), None);
+            }
+        }
+    }
+
+    #[test]
+    fn timer_tick_countdown_follows_the_ticks() {
+        // Count the delay timer down from 5 while spinning
+        let mut interp = interpreter(
+            Chip8Variant::ChipModern,
+            &[0x60, 0x05, 0xf0, 0x15, 0x12, 0x04],
+        );
+        interp.set_clock_rate(600);
+        assert_eq!(interp.instructions_until_timer_tick(), Some(10));
+
+        interp.run_cycles(7).unwrap();
+        assert_eq!(interp.instructions_until_timer_tick(), Some(3));
+        assert_eq!(interp.state().dt, 5);
+
+        interp.run_cycles(3).unwrap();
+        assert_eq!(interp.instructions_until_timer_tick(), Some(10));
+        assert_eq!(interp.state().dt, 4);
+
+        interp.set_tick_drives_timers(false);
+        assert_eq!(interp.instructions_until_timer_tick(), None);
+    }
+
+    #[test]
+    fn reset_keeps_or_clears_debug_state_as_configured() {
+        for reset_clears_debug_state in [false, true] {
+            let config = Chip8Config {
+                reset_clears_debug_state,
+                ..Default::default()
+            };
+            let mut interp = interpreter_with_config(config, &[0x12, 0x00]);
+            interp.add_breakpoint(0x200);
+            interp.add_watchpoint(0x300);
+            interp.freeze_timers(true);
+
+            interp.reset();
+            assert_eq!(interp.breakpoints().is_empty(), reset_clears_debug_state);
+            assert_eq!(interp.watchpoints().is_empty(), reset_clears_debug_state);
+            assert_eq!(interp.timers_frozen(), !reset_clears_debug_state);
+
+            interp.clear_debug_state();
+            assert!(interp.breakpoints().is_empty());
+            assert!(interp.watchpoints().is_empty());
+            assert!(!interp.timers_frozen());
+        }
+    }
+
+    #[test]
+    fn reloading_changed_data_patches_memory_in_place() {
+        // I := 206, V0 := 1, then spin above the sprite byte
+        let rom = [0xa2, 0x06, 0x60, 0x01, 0x12, 0x04, 0xf0];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        interp.run_cycles(3).unwrap();
+
+        let mut edited = rom;
+        edited[6] = 0x0f;
+        assert!(!interp.reload_rom(&edited).unwrap());
+        assert_eq!(interp.state().pc, 0x204);
+        assert_eq!(interp.state().registers[0], 1);
+        assert_eq!(interp.state().memory[0x206], 0x0f);
+        assert_eq!(interp.rom(), edited);
+    }
+
+    #[test]
+    fn reloading_changed_code_resets() {
+        let rom = [0xa2, 0x06, 0x60, 0x01, 0x12, 0x04, 0xf0];
+        let mut interp = interpreter(Chip8Variant::ChipModern, &rom);
+        interp.run_cycles(3).unwrap();
+
+        let mut edited = rom;
+        edited[3] = 0x02;
+        assert!(interp.reload_rom(&edited).unwrap());
+        assert_eq!(interp.state().pc, BASE_ADDRESS);
+        assert_eq!(interp.state().registers[0], 0);
+        assert_eq!(interp.state().memory[0x203], 0x02);
+
+        // A ROM of a different size is reloaded from scratch too
+        interp.run_cycles(3).unwrap();
+        assert!(interp.reload_rom(&rom[..6]).unwrap());
+        assert_eq!(interp.state().pc, BASE_ADDRESS);
+        assert_eq!(interp.rom(), &rom[..6]);
+    }
+
+    #[test]
+    fn resolution_switches_clear_the_screen_only_with_quirk() {
+        // Draw font sprites on both planes, switch to hires and back to lores
+        let rom = [0xf3, 0x01, 0xd0, 0x15, 0x00, 0xff, 0x00, 0xfe];
+        for resolution_switch_clears_screen in [false, true] {
+            let config = Chip8Config {
+                variant: Chip8Variant::XoChip,
+                quirks: Quirks {
+                    resolution_switch_clears_screen,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut interp = interpreter_with_config(config, &rom);
+            interp.run_cycles(2).unwrap();
+            let drawn = interp.state().screen;
+            assert!(drawn.iter().flatten().any(|&pixel| pixel == 3));
+
+            for _ in 0..2 {
+                interp.tick().unwrap();
+                if resolution_switch_clears_screen {
+                    assert_eq!(lit_pixels(&interp), 0);
+                } else {
+                    assert!(interp.state().screen == drawn);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// Source of the random bytes `Random` masks
+///
+/// Every cloneable `rand` generator is one. Set a generator with `Chip8Interpreter::set_rng` to
+/// replay the same random bytes, for example in tests.
+pub trait Rng {
+    fn next_u8(&mut self) -> u8;
+    /// Copy of the generator that produces the same bytes from here on, kept by save states
+    fn boxed_clone(&self) -> Box<dyn Rng>;
+}
+
+impl<R: RngCore + Clone + 'static> Rng for R {
+    fn next_u8(&mut self) -> u8 {
+        self.next_u32() as u8
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Rng> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Rng> {
+    fn clone(&self) -> Self {
+        self.boxed_clone()
+    }
+}
+
+/// Generator used unless another one is set, seeded from the operating system
+///
+/// Unlike the thread generator of `rand` it can be copied into save states, so loading a state
+/// replays the same random bytes.
+pub(super) fn entropy_rng() -> Box<dyn Rng> {
+    Box::new(StdRng::from_entropy())
+}
+
+/// Generator that always produces the same bytes for the same seed
+pub(super) fn seeded_rng(seed: u64) -> Box<dyn Rng> {
+    Box::new(StdRng::seed_from_u64(seed))
+}
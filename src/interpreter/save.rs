@@ -0,0 +1,77 @@
+use super::rng::{self, Rng};
+use super::{Chip8Config, Chip8InterpreterState};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Everything needed to resume execution exactly where it was saved
+///
+/// The random number generator state is not serialized, a deserialized save starts from a freshly
+/// seeded generator.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Chip8SaveState {
+    pub(super) state: Chip8InterpreterState,
+    #[serde(skip, default = "rng::entropy_rng")]
+    pub(super) rng: Box<dyn Rng>,
+    pub(super) timer_counter: usize,
+    #[serde(alias = "cycles")]
+    pub(super) ticks: u64,
+    pub(super) scheduled_input: BTreeMap<u64, u32>,
+    /// Keys released since the last executed instruction, which `WaitForKey` may still take
+    #[serde(default)]
+    pub(super) released_keys: u32,
+}
+
+impl Chip8SaveState {
+    pub fn state(&self) -> &Chip8InterpreterState {
+        &self.state
+    }
+}
+
+/// Save state together with the configuration it was made under, so that loading it also restores
+/// the variant and quirks it expects
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Chip8SaveBundle {
+    /// Format version of the bundle, checked when loading
+    pub version: u32,
+    pub config: Chip8Config,
+    pub save: Chip8SaveState,
+}
+
+impl Chip8SaveBundle {
+    /// Bundle format version written by this build
+    pub const VERSION: u32 = 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Chip8Interpreter, Quirks};
+
+    #[test]
+    fn bundles_from_before_newer_config_fields_load() {
+        let mut bundle =
+            serde_json::to_value(Chip8Interpreter::with_seed(0).save_bundle()).unwrap();
+        let config = bundle["config"].as_object_mut().unwrap();
+        for field in [
+            "wait_for_key_release",
+            "reset_clears_debug_state",
+            "clock_hz",
+            "initial_pc",
+            "quirks",
+        ] {
+            config.remove(field).unwrap();
+        }
+
+        let bundle: Chip8SaveBundle = serde_json::from_value(bundle).unwrap();
+        assert_eq!(bundle.config.initial_pc, None);
+        assert_eq!(bundle.config.quirks, Default::default());
+        Chip8Interpreter::new().load_bundle(bundle).unwrap();
+    }
+
+    #[test]
+    fn quirks_missing_from_a_config_are_off() {
+        let quirks: Quirks = serde_json::from_str(r#"{"shift_uses_vy": true}"#).unwrap();
+        assert!(quirks.shift_uses_vy);
+        assert!(!quirks.memory_increments_i);
+    }
+}
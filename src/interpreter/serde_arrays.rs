@@ -0,0 +1,62 @@
+//! Serde helpers for the memory and screen arrays, which are too large for serde's built in array
+//! support
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<S, T, const N: usize>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    array.as_slice().serialize(serializer)
+}
+
+pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let values = Vec::<T>::deserialize(deserializer)?;
+    let len = values.len();
+    values
+        .try_into()
+        .map_err(|_| D::Error::invalid_length(len, &"an array of the expected length"))
+}
+
+/// Same as the parent module, for two dimensional arrays
+pub mod nested {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S, T, const W: usize, const H: usize>(
+        array: &[[T; W]; H],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize,
+    {
+        serializer.collect_seq(array.iter().map(|row| row.as_slice()))
+    }
+
+    pub fn deserialize<'de, D, T, const W: usize, const H: usize>(
+        deserializer: D,
+    ) -> Result<[[T; W]; H], D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        let rows = Vec::<Vec<T>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|row| {
+                let len = row.len();
+                row.try_into()
+                    .map_err(|_| D::Error::invalid_length(len, &"a row of the expected length"))
+            })
+            .collect::<Result<Vec<[T; W]>, D::Error>>()?;
+        let len = rows.len();
+        rows.try_into()
+            .map_err(|_| D::Error::invalid_length(len, &"an array of the expected length"))
+    }
+}
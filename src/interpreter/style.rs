@@ -0,0 +1,256 @@
+use super::instructions::Chip8Instruction;
+use serde::{Deserialize, Serialize};
+
+/// Mnemonic style instructions are written in by `Chip8Instruction::format`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DisassemblyStyle {
+    /// The `Display` output of the instruction, e.g. `V3 := V3 - V4`
+    #[default]
+    Verbose,
+    /// Octo assembly, e.g. `v3 -= v4`
+    Octo,
+    /// Classic CHIP-8 assembly mnemonics, e.g. `SUB V3, V4`
+    Assembly,
+}
+
+impl DisassemblyStyle {
+    pub const ALL: [DisassemblyStyle; 3] = [
+        DisassemblyStyle::Verbose,
+        DisassemblyStyle::Octo,
+        DisassemblyStyle::Assembly,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisassemblyStyle::Verbose => "Verbose",
+            DisassemblyStyle::Octo => "Octo",
+            DisassemblyStyle::Assembly => "Assembly",
+        }
+    }
+}
+
+impl Chip8Instruction {
+    /// Write the instruction in the given mnemonic style
+    pub fn format(&self, style: DisassemblyStyle) -> String {
+        match style {
+            DisassemblyStyle::Verbose => self.to_string(),
+            DisassemblyStyle::Octo => self.format_octo(),
+            DisassemblyStyle::Assembly => self.format_assembly(),
+        }
+    }
+
+    fn format_octo(&self) -> String {
+        match self {
+            // Octo has no mnemonic for either, machine code calls are written as raw bytes
+            Chip8Instruction::NoOperation => "# no-op".to_string(),
+            Chip8Instruction::Syscall { address } => {
+                format!("0x{:02x} 0x{:02x}", address >> 8, address & 0xff)
+            }
+            Chip8Instruction::Random { register, mask } => {
+                format!("v{:x} := random 0x{:02x}", register, mask)
+            }
+            Chip8Instruction::Call { address } => format!(":call 0x{:03x}", address),
+            Chip8Instruction::Return => "return".to_string(),
+            Chip8Instruction::Exit => "exit".to_string(),
+            Chip8Instruction::StoreRegisters { count } => format!("save v{:x}", count - 1),
+            Chip8Instruction::LoadRegisters { count } => format!("load v{:x}", count - 1),
+            Chip8Instruction::StoreFlags { count } => format!("saveflags v{:x}", count - 1),
+            Chip8Instruction::LoadFlags { count } => format!("loadflags v{:x}", count - 1),
+            Chip8Instruction::StoreRegisterRange { x, y } => format!("save v{:x} - v{:x}", x, y),
+            Chip8Instruction::LoadRegisterRange { x, y } => format!("load v{:x} - v{:x}", x, y),
+            Chip8Instruction::Jump { address } => format!("jump 0x{:03x}", address),
+            Chip8Instruction::JumpRelative { address } => format!("jump0 0x{:03x}", address),
+            Chip8Instruction::ClearScreen => "clear".to_string(),
+            Chip8Instruction::ScrollDown { amount } => format!("scroll-down {}", amount),
+            Chip8Instruction::ScrollRight => "scroll-right".to_string(),
+            Chip8Instruction::ScrollLeft => "scroll-left".to_string(),
+            Chip8Instruction::LowResolution => "lores".to_string(),
+            Chip8Instruction::HighResolution => "hires".to_string(),
+            Chip8Instruction::SelectCharacter { register } => format!("i := hex v{:x}", register),
+            Chip8Instruction::StoreBcd { register } => format!("bcd v{:x}", register),
+            Chip8Instruction::Draw { x, y, len } => format!("sprite v{:x} v{:x} {}", x, y, len),
+            Chip8Instruction::SelectPlane { mask } => format!("plane {}", mask),
+            // Octo writes skips as the condition under which the next instruction runs
+            Chip8Instruction::SkipIfEqualValue { register, value } => {
+                format!("if v{:x} != {} then", register, value)
+            }
+            Chip8Instruction::SkipIfEqualRegister { x, y } => {
+                format!("if v{:x} != v{:x} then", x, y)
+            }
+            Chip8Instruction::SkipIfNotEqualValue { register, value } => {
+                format!("if v{:x} == {} then", register, value)
+            }
+            Chip8Instruction::SkipIfNotEqualRegister { x, y } => {
+                format!("if v{:x} == v{:x} then", x, y)
+            }
+            Chip8Instruction::SkipIfKeyPressed { register } => {
+                format!("if v{:x} -key then", register)
+            }
+            Chip8Instruction::SkipIfKeyNotPressed { register } => {
+                format!("if v{:x} key then", register)
+            }
+            Chip8Instruction::SetIndex { address } => format!("i := 0x{:03x}", address),
+            Chip8Instruction::LoadLongIndex { address } => format!("i := long 0x{:04x}", address),
+            Chip8Instruction::AddIndex { register } => format!("i += v{:x}", register),
+            Chip8Instruction::LoadValue { register, value } => {
+                format!("v{:x} := {}", register, value)
+            }
+            Chip8Instruction::Copy { x, y } => format!("v{:x} := v{:x}", x, y),
+            Chip8Instruction::ReadDelayTimer { register } => format!("v{:x} := delay", register),
+            Chip8Instruction::SetDelayTimer { register } => format!("delay := v{:x}", register),
+            Chip8Instruction::SetSoundTimer { register } => format!("buzzer := v{:x}", register),
+            Chip8Instruction::WaitForKey { register } => format!("v{:x} := key", register),
+            Chip8Instruction::AddValue { register, value } => {
+                format!("v{:x} += {}", register, value)
+            }
+            Chip8Instruction::AddRegister { x, y } => format!("v{:x} += v{:x}", x, y),
+            Chip8Instruction::SubtractVxVy { x, y } => format!("v{:x} -= v{:x}", x, y),
+            Chip8Instruction::SubtractVyVx { x, y } => format!("v{:x} =- v{:x}", x, y),
+            Chip8Instruction::Or { x, y } => format!("v{:x} |= v{:x}", x, y),
+            Chip8Instruction::And { x, y } => format!("v{:x} &= v{:x}", x, y),
+            Chip8Instruction::Xor { x, y } => format!("v{:x} ^= v{:x}", x, y),
+            Chip8Instruction::ShiftRight { x, y } => format!("v{:x} >>= v{:x}", x, y),
+            Chip8Instruction::ShiftLeft { x, y } => format!("v{:x} <<= v{:x}", x, y),
+        }
+    }
+
+    fn format_assembly(&self) -> String {
+        match self {
+            Chip8Instruction::NoOperation => "NOP".to_string(),
+            Chip8Instruction::Syscall { address } => format!("SYS 0x{:03x}", address),
+            Chip8Instruction::Random { register, mask } => {
+                format!("RND V{:X}, 0x{:02x}", register, mask)
+            }
+            Chip8Instruction::Call { address } => format!("CALL 0x{:03x}", address),
+            Chip8Instruction::Return => "RET".to_string(),
+            Chip8Instruction::Exit => "EXIT".to_string(),
+            Chip8Instruction::StoreRegisters { count } => format!("LD [I], V{:X}", count - 1),
+            Chip8Instruction::LoadRegisters { count } => format!("LD V{:X}, [I]", count - 1),
+            Chip8Instruction::StoreFlags { count } => format!("LD R, V{:X}", count - 1),
+            Chip8Instruction::LoadFlags { count } => format!("LD V{:X}, R", count - 1),
+            Chip8Instruction::StoreRegisterRange { x, y } => format!("SAVE V{:X} - V{:X}", x, y),
+            Chip8Instruction::LoadRegisterRange { x, y } => format!("LOAD V{:X} - V{:X}", x, y),
+            Chip8Instruction::Jump { address } => format!("JP 0x{:03x}", address),
+            Chip8Instruction::JumpRelative { address } => format!("JP V0, 0x{:03x}", address),
+            Chip8Instruction::ClearScreen => "CLS".to_string(),
+            Chip8Instruction::ScrollDown { amount } => format!("SCD {}", amount),
+            Chip8Instruction::ScrollRight => "SCR".to_string(),
+            Chip8Instruction::ScrollLeft => "SCL".to_string(),
+            Chip8Instruction::LowResolution => "LOW".to_string(),
+            Chip8Instruction::HighResolution => "HIGH".to_string(),
+            Chip8Instruction::SelectCharacter { register } => format!("LD F, V{:X}", register),
+            Chip8Instruction::StoreBcd { register } => format!("LD B, V{:X}", register),
+            Chip8Instruction::Draw { x, y, len } => format!("DRW V{:X}, V{:X}, {}", x, y, len),
+            Chip8Instruction::SelectPlane { mask } => format!("PLANE {}", mask),
+            Chip8Instruction::SkipIfEqualValue { register, value } => {
+                format!("SE V{:X}, 0x{:02x}", register, value)
+            }
+            Chip8Instruction::SkipIfEqualRegister { x, y } => format!("SE V{:X}, V{:X}", x, y),
+            Chip8Instruction::SkipIfNotEqualValue { register, value } => {
+                format!("SNE V{:X}, 0x{:02x}", register, value)
+            }
+            Chip8Instruction::SkipIfNotEqualRegister { x, y } => {
+                format!("SNE V{:X}, V{:X}", x, y)
+            }
+            Chip8Instruction::SkipIfKeyPressed { register } => format!("SKP V{:X}", register),
+            Chip8Instruction::SkipIfKeyNotPressed { register } => format!("SKNP V{:X}", register),
+            Chip8Instruction::SetIndex { address } => format!("LD I, 0x{:03x}", address),
+            Chip8Instruction::LoadLongIndex { address } => {
+                format!("LD I, LONG 0x{:04x}", address)
+            }
+            Chip8Instruction::AddIndex { register } => format!("ADD I, V{:X}", register),
+            Chip8Instruction::LoadValue { register, value } => {
+                format!("LD V{:X}, 0x{:02x}", register, value)
+            }
+            Chip8Instruction::Copy { x, y } => format!("LD V{:X}, V{:X}", x, y),
+            Chip8Instruction::ReadDelayTimer { register } => format!("LD V{:X}, DT", register),
+            Chip8Instruction::SetDelayTimer { register } => format!("LD DT, V{:X}", register),
+            Chip8Instruction::SetSoundTimer { register } => format!("LD ST, V{:X}", register),
+            Chip8Instruction::WaitForKey { register } => format!("LD V{:X}, K", register),
+            Chip8Instruction::AddValue { register, value } => {
+                format!("ADD V{:X}, 0x{:02x}", register, value)
+            }
+            Chip8Instruction::AddRegister { x, y } => format!("ADD V{:X}, V{:X}", x, y),
+            Chip8Instruction::SubtractVxVy { x, y } => format!("SUB V{:X}, V{:X}", x, y),
+            Chip8Instruction::SubtractVyVx { x, y } => format!("SUBN V{:X}, V{:X}", x, y),
+            Chip8Instruction::Or { x, y } => format!("OR V{:X}, V{:X}", x, y),
+            Chip8Instruction::And { x, y } => format!("AND V{:X}, V{:X}", x, y),
+            Chip8Instruction::Xor { x, y } => format!("XOR V{:X}, V{:X}", x, y),
+            Chip8Instruction::ShiftRight { x, y } => format!("SHR V{:X}, V{:X}", x, y),
+            Chip8Instruction::ShiftLeft { x, y } => format!("SHL V{:X}, V{:X}", x, y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(instruction: Chip8Instruction) -> [String; 3] {
+        DisassemblyStyle::ALL.map(|style| instruction.format(style))
+    }
+
+    #[test]
+    fn arithmetic_in_every_style() {
+        assert_eq!(
+            formatted(Chip8Instruction::SubtractVxVy { x: 3, y: 4 }),
+            ["V3 := V3 - V4", "v3 -= v4", "SUB V3, V4"]
+        );
+        assert_eq!(
+            formatted(Chip8Instruction::AddValue {
+                register: 0xa,
+                value: 5
+            }),
+            ["Va += 5", "va += 5", "ADD VA, 0x05"]
+        );
+    }
+
+    #[test]
+    fn draw_and_index_in_every_style() {
+        assert_eq!(
+            formatted(Chip8Instruction::Draw { x: 1, y: 2, len: 5 }),
+            [
+                "Draw(x: 1, y: 2, length: 5)",
+                "sprite v1 v2 5",
+                "DRW V1, V2, 5"
+            ]
+        );
+        let [_, octo, assembly] = formatted(Chip8Instruction::SetIndex { address: 0x2a0 });
+        assert_eq!(octo, "i := 0x2a0");
+        assert_eq!(assembly, "LD I, 0x2a0");
+    }
+
+    #[test]
+    fn octo_skips_are_written_as_the_inverted_condition() {
+        let [verbose, octo, assembly] = formatted(Chip8Instruction::SkipIfEqualValue {
+            register: 2,
+            value: 7,
+        });
+        assert_eq!(verbose, "SkipNext if V2 == 7");
+        assert_eq!(octo, "if v2 != 7 then");
+        assert_eq!(assembly, "SE V2, 0x07");
+
+        let [_, octo, assembly] =
+            formatted(Chip8Instruction::SkipIfNotEqualRegister { x: 1, y: 5 });
+        assert_eq!(octo, "if v1 == v5 then");
+        assert_eq!(assembly, "SNE V1, V5");
+
+        let [verbose, octo, assembly] =
+            formatted(Chip8Instruction::SkipIfKeyPressed { register: 0xe });
+        assert_eq!(verbose, "SkipNext if Key[Ve] == Pressed");
+        assert_eq!(octo, "if ve -key then");
+        assert_eq!(assembly, "SKP VE");
+
+        let [_, octo, assembly] = formatted(Chip8Instruction::SkipIfKeyNotPressed { register: 0 });
+        assert_eq!(octo, "if v0 key then");
+        assert_eq!(assembly, "SKNP V0");
+    }
+
+    #[test]
+    fn register_counts_name_the_last_register() {
+        assert_eq!(
+            formatted(Chip8Instruction::StoreRegisters { count: 16 }),
+            ["StoreRegisters(16)", "save vf", "LD [I], VF"]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use super::{Chip8Interpreter, Chip8InterpreterError, REGISTER_COUNT};
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[error("Line {line}: {message}")]
+pub struct TraceParseError {
+    /// Line of the trace file the error is on, starting at 1
+    pub line: usize,
+    pub message: String,
+}
+
+/// State expected before one instruction of a reference trace is executed
+///
+/// In a trace file every step is a line with the PC, optionally followed by the values of all of
+/// V0 to VF, everything in hexadecimal:
+///
+/// ```text
+/// # Comments and blank lines are ignored
+/// 0200
+/// 0202 05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceStep {
+    pub pc: u16,
+    /// Registers aren't compared for steps that don't list them
+    pub registers: Option<[u8; REGISTER_COUNT]>,
+}
+
+impl Display for TraceStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04x}", self.pc)?;
+        if let Some(registers) = self.registers {
+            for value in registers {
+                write!(f, " {:02x}", value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a reference trace in the format described at `TraceStep`
+pub fn parse_trace(source: &str) -> Result<Vec<TraceStep>, TraceParseError> {
+    let mut steps = vec![];
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| TraceParseError {
+            line: index + 1,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let pc = match fields.next() {
+            Some(pc) => {
+                u16::from_str_radix(pc, 16).map_err(|_| error(format!("invalid PC {}", pc)))?
+            }
+            None => continue,
+        };
+
+        let values = fields
+            .map(|value| {
+                u8::from_str_radix(value, 16)
+                    .map_err(|_| error(format!("invalid register value {}", value)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let registers = match values.len() {
+            0 => None,
+            REGISTER_COUNT => values.try_into().ok(),
+            count => {
+                return Err(error(format!(
+                    "expected {} register values, found {}",
+                    REGISTER_COUNT, count
+                )))
+            }
+        };
+
+        steps.push(TraceStep { pc, registers });
+    }
+    Ok(steps)
+}
+
+/// First step of a reference trace the interpreter didn't match
+#[derive(Debug)]
+pub struct TraceMismatch {
+    /// Index of the step in the trace
+    pub step: usize,
+    pub expected: TraceStep,
+    /// Interpreter state in the same form as `expected`
+    pub actual: TraceStep,
+}
+
+impl Display for TraceMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Step {} differs from the reference trace", self.step)?;
+        writeln!(f, "expected: {}", self.expected)?;
+        write!(f, "actual:   {}", self.actual)
+    }
+}
+
+/// Step the interpreter through `trace`, comparing its state before every instruction
+///
+/// Returns the first step that doesn't match, or `None` if the whole trace matched. Errors of the
+/// interpreter end the comparison.
+pub fn compare_trace(
+    interpreter: &mut Chip8Interpreter,
+    trace: &[TraceStep],
+) -> Result<Option<TraceMismatch>, Chip8InterpreterError> {
+    for (step, expected) in trace.iter().enumerate() {
+        let state = interpreter.state();
+        let actual = TraceStep {
+            pc: state.pc,
+            registers: expected.registers.map(|_| state.registers),
+        };
+        if actual != *expected {
+            return Ok(Some(TraceMismatch {
+                step,
+                expected: *expected,
+                actual,
+            }));
+        }
+
+        if step + 1 < trace.len() {
+            interpreter.step()?;
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE: &str = "
+# V0 := 5, V0 += 3, then loop
+0200
+0202 05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+0204 08 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+0204
+";
+
+    fn interpreter() -> Chip8Interpreter {
+        let mut interp = Chip8Interpreter::new();
+        interp
+            .try_load_rom(&[0x60, 0x05, 0x70, 0x03, 0x12, 0x04])
+            .unwrap();
+        interp
+    }
+
+    #[test]
+    fn matching_trace_has_no_mismatch() {
+        let trace = parse_trace(TRACE).unwrap();
+        assert_eq!(trace.len(), 4);
+        assert!(compare_trace(&mut interpreter(), &trace).unwrap().is_none());
+    }
+
+    #[test]
+    fn first_divergence_is_reported() {
+        let trace = parse_trace(&TRACE.replace("0204 08", "0204 09")).unwrap();
+        let mismatch = compare_trace(&mut interpreter(), &trace).unwrap().unwrap();
+        assert_eq!(mismatch.step, 2);
+        assert_eq!(mismatch.expected.registers.unwrap()[0], 0x09);
+        assert_eq!(mismatch.actual.registers.unwrap()[0], 0x08);
+    }
+
+    #[test]
+    fn malformed_lines_are_reported() {
+        let error = parse_trace("0200\n0202 05 00\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(parse_trace("02zz").is_err());
+    }
+}
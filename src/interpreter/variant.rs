@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Groups of instructions introduced by the different CHIP-8 extensions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum InstructionSet {
+    /// Instructions of the original COSMAC VIP interpreter
+    Chip8,
+    /// Instructions added by SUPER-CHIP 1.1
+    SuperChip,
+    /// Instructions added by XO-CHIP
+    XoChip,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Chip8Variant {
+    /// Original CHIP-8 interpreter for the COSMAC VIP
+    CosmacVip,
+    /// SUPER-CHIP 1.1 for the HP48 calculators
+    SuperChip,
+    /// XO-CHIP as implemented by Octo
+    XoChip,
+    /// CHIP-8 as commonly implemented by modern interpreters
+    #[default]
+    ChipModern,
+}
+
+impl Chip8Variant {
+    pub const ALL: [Chip8Variant; 4] = [
+        Chip8Variant::CosmacVip,
+        Chip8Variant::SuperChip,
+        Chip8Variant::XoChip,
+        Chip8Variant::ChipModern,
+    ];
+
+    /// Instruction sets available under this variant
+    pub fn instruction_sets(&self) -> &'static [InstructionSet] {
+        match self {
+            Chip8Variant::CosmacVip | Chip8Variant::ChipModern => &[InstructionSet::Chip8],
+            Chip8Variant::SuperChip => &[InstructionSet::Chip8, InstructionSet::SuperChip],
+            Chip8Variant::XoChip => &[
+                InstructionSet::Chip8,
+                InstructionSet::SuperChip,
+                InstructionSet::XoChip,
+            ],
+        }
+    }
+
+    pub fn supports(&self, instruction_set: InstructionSet) -> bool {
+        self.instruction_sets().contains(&instruction_set)
+    }
+
+    /// Clock speed in instructions per second that programs written for this variant expect
+    pub fn default_clock_hz(&self) -> u32 {
+        match self {
+            Chip8Variant::CosmacVip => 600,
+            Chip8Variant::SuperChip => 1000,
+            Chip8Variant::XoChip => 1000,
+            // The 20 instructions per 60Hz frame the app always ran at
+            Chip8Variant::ChipModern => 1200,
+        }
+    }
+}
+
+impl Display for InstructionSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstructionSet::Chip8 => write!(f, "CHIP-8"),
+            InstructionSet::SuperChip => write!(f, "SUPER-CHIP"),
+            InstructionSet::XoChip => write!(f, "XO-CHIP"),
+        }
+    }
+}
+
+impl Display for Chip8Variant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Chip8Variant::CosmacVip => write!(f, "COSMAC VIP"),
+            Chip8Variant::SuperChip => write!(f, "SUPER-CHIP"),
+            Chip8Variant::XoChip => write!(f, "XO-CHIP"),
+            Chip8Variant::ChipModern => write!(f, "Modern CHIP-8"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{
+        Chip8Config, Chip8Instruction, Chip8Interpreter, Chip8InterpreterError, TIMER_FREQUENCY,
+    };
+
+    #[test]
+    fn default_clock_follows_variant() {
+        let mut interp = Chip8Interpreter::new();
+        assert_eq!(interp.clock_rate(), 20 * TIMER_FREQUENCY);
+
+        for variant in Chip8Variant::ALL {
+            interp.set_config(Chip8Config {
+                variant,
+                ..Default::default()
+            });
+            assert_eq!(interp.clock_rate(), variant.default_clock_hz());
+        }
+    }
+
+    #[test]
+    fn clock_override_survives_variant_change() {
+        let mut interp = Chip8Interpreter::new();
+        interp.set_clock_rate(3000);
+        let mut config = *interp.config();
+        config.variant = Chip8Variant::CosmacVip;
+        interp.set_config(config);
+        assert_eq!(interp.clock_rate(), 3000);
+    }
+
+    #[test]
+    fn scroll_needs_super_chip() {
+        let decode_scroll = |variant| {
+            let mut interp = Chip8Interpreter::new();
+            interp.set_config(Chip8Config {
+                variant,
+                ..Default::default()
+            });
+            interp.try_load_rom(&[0x00, 0xc4]).unwrap();
+            interp.try_read_instruction(0x200)
+        };
+
+        assert!(matches!(
+            decode_scroll(Chip8Variant::CosmacVip),
+            Err(Chip8InterpreterError::InvalidInstruction(0x00c4))
+        ));
+        assert!(matches!(
+            decode_scroll(Chip8Variant::SuperChip),
+            Ok(Chip8Instruction::ScrollDown { amount: 4 })
+        ));
+    }
+}
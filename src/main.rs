@@ -8,10 +8,98 @@ use eframe::egui::CursorIcon::Default;
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    let app = chippie::TemplateApp::default();
+    let mut args = std::env::args_os().skip(1).peekable();
+    if args.peek().is_some_and(|arg| *arg == "--disassemble-json") {
+        args.next();
+        std::process::exit(match args.next() {
+            Some(path) => disassemble_json(path.as_ref()),
+            None => {
+                eprintln!("Usage: chippie --disassemble-json <rom>");
+                2
+            }
+        });
+    }
+    if args.peek().is_some_and(|arg| *arg == "--compare-trace") {
+        args.next();
+        std::process::exit(match (args.next(), args.next()) {
+            (Some(rom), Some(trace)) => compare_trace(rom.as_ref(), trace.as_ref()),
+            _ => {
+                eprintln!("Usage: chippie --compare-trace <rom> <trace>");
+                2
+            }
+        });
+    }
+
+    let mut app = chippie::TemplateApp::default();
+    let playlist: Vec<_> = args.map(Into::into).collect();
+    if !playlist.is_empty() {
+        app.set_playlist(playlist);
+    }
     let native_options = eframe::NativeOptions {
         maximized: true,
+        drag_and_drop_support: true,
         ..eframe::NativeOptions::default()
     };
     eframe::run_native(Box::new(app), native_options);
 }
+
+/// Run the ROM at `rom_path` against the reference trace at `trace_path`, returning the process
+/// exit code
+#[cfg(not(target_arch = "wasm32"))]
+fn compare_trace(rom_path: &std::path::Path, trace_path: &std::path::Path) -> i32 {
+    use chippie::interpreter::trace;
+
+    let mut interpreter = chippie::interpreter::Chip8Interpreter::new();
+    let result = std::fs::read(rom_path)
+        .map_err(|e| format!("Unable to read {}: {}", rom_path.display(), e))
+        .and_then(|rom| {
+            interpreter
+                .try_load_rom(&rom)
+                .map_err(|e| format!("Unable to load {}: {}", rom_path.display(), e))
+        })
+        .and_then(|()| {
+            std::fs::read_to_string(trace_path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| trace::parse_trace(&source).map_err(|e| e.to_string()))
+                .map_err(|e| format!("Unable to read {}: {}", trace_path.display(), e))
+        })
+        .and_then(|steps| {
+            trace::compare_trace(&mut interpreter, &steps)
+                .map_err(|e| format!("Interpreter error: {}", e))
+        });
+    match result {
+        Ok(None) => {
+            println!("Trace matches");
+            0
+        }
+        Ok(Some(mismatch)) => {
+            println!("{}", mismatch);
+            1
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// Print the disassembly of the ROM at `path` as JSON, returning the process exit code
+#[cfg(not(target_arch = "wasm32"))]
+fn disassemble_json(path: &std::path::Path) -> i32 {
+    let mut interpreter = chippie::interpreter::Chip8Interpreter::new();
+    let result = std::fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|rom| interpreter.try_load_rom(&rom).map_err(|e| e.to_string()))
+        .and_then(|()| {
+            interpreter
+                .write_disassembly_json(&mut std::io::stdout().lock(), |_| None)
+                .map_err(|e| e.to_string())
+        });
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Unable to disassemble {}: {}", path.display(), e);
+            1
+        }
+    }
+}